@@ -0,0 +1,281 @@
+use crate::error::AppError;
+use crate::providers::ProviderKind;
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::Manager;
+
+pub const DEFAULT_HOST: &str = "github.com";
+pub const DEFAULT_SCHEME: &str = "https";
+
+fn default_scheme() -> String {
+    DEFAULT_SCHEME.to_string()
+}
+
+/// Non-secret settings stored for each account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountConfig {
+    // Accounts saved before providers existed are GitHub accounts
+    #[serde(default)]
+    pub provider: ProviderKind,
+    pub host: String,
+    /// `http` for self-hosted GitLab and Gitea instances served without TLS
+    #[serde(default = "default_scheme")]
+    pub scheme: String,
+}
+
+impl AccountConfig {
+    /// The provider's public instance
+    pub fn public(provider: ProviderKind) -> Self {
+        AccountConfig {
+            provider,
+            host: provider.default_host().to_string(),
+            scheme: default_scheme(),
+        }
+    }
+
+    /// Settings for `provider` at a host or URL entered by the user. GitHub
+    /// URLs are always built with https, so `http://` is refused there.
+    pub fn parse(provider: ProviderKind, input: &str) -> Result<Self, AppError> {
+        let (scheme, host) = normalize_origin(input)?;
+        if provider == ProviderKind::GitHub && scheme != DEFAULT_SCHEME {
+            return Err(AppError::invalid_input(format!(
+                "GitHub hosts are only reachable over https, not '{}'",
+                input
+            )));
+        }
+
+        Ok(AccountConfig {
+            provider,
+            host,
+            scheme,
+        })
+    }
+
+    /// Web root of the host, such as `https://gitlab.com`
+    pub fn base_url(&self) -> String {
+        format!("{}://{}", self.scheme, self.host)
+    }
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        AccountConfig::public(ProviderKind::GitHub)
+    }
+}
+
+/// An entry in the account index. Its token lives in the keyring under `id`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub provider: ProviderKind,
+    pub host: String,
+    #[serde(default = "default_scheme")]
+    pub scheme: String,
+    pub login: String,
+    pub label: Option<String>,
+}
+
+impl Account {
+    pub fn new(provider: ProviderKind, host: &str, login: &str, label: Option<String>) -> Self {
+        Account {
+            id: format!("{}:{}@{}", provider.as_str(), login, host),
+            provider,
+            host: host.to_string(),
+            scheme: default_scheme(),
+            login: login.to_string(),
+            label,
+        }
+    }
+
+    /// The same account reached over `scheme` instead of https
+    pub fn with_scheme(self, scheme: &str) -> Self {
+        Account {
+            scheme: scheme.to_string(),
+            ..self
+        }
+    }
+
+    pub fn config(&self) -> AccountConfig {
+        AccountConfig {
+            provider: self.provider,
+            host: self.host.clone(),
+            scheme: self.scheme.clone(),
+        }
+    }
+}
+
+/// Non-secret account metadata, persisted in the settings file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountIndex {
+    pub active: Option<String>,
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+}
+
+impl AccountIndex {
+    pub fn find(&self, id: &str) -> Option<&Account> {
+        self.accounts.iter().find(|account| account.id == id)
+    }
+
+    pub fn active_account(&self) -> Option<&Account> {
+        self.active.as_deref().and_then(|id| self.find(id))
+    }
+
+    /// Insert `account`, replacing any entry with the same id but keeping
+    /// its label when the new one has none
+    pub fn upsert(&mut self, mut account: Account) {
+        match self.accounts.iter_mut().find(|a| a.id == account.id) {
+            Some(existing) => {
+                if account.label.is_none() {
+                    account.label = existing.label.take();
+                }
+                *existing = account;
+            }
+            None => self.accounts.push(account),
+        }
+    }
+
+    /// Remove an account, moving the active marker to the next one if needed
+    pub fn remove(&mut self, id: &str) -> Option<Account> {
+        let position = self.accounts.iter().position(|account| account.id == id)?;
+        let removed = self.accounts.remove(position);
+
+        if self.active.as_deref() == Some(id) {
+            self.active = self.accounts.first().map(|account| account.id.clone());
+        }
+
+        Some(removed)
+    }
+}
+
+/// Normalize a host or URL entered by the user (`https://ghe.example.com/`,
+/// `ghe.example.com/api/graphql`, `api.github.com`, ...) to a bare host
+pub fn normalize_host(input: &str) -> Result<String, AppError> {
+    normalize_origin(input).map(|(_, host)| host)
+}
+
+/// Like `normalize_host`, also returning the URL's scheme (`https` when the
+/// input has none). Only `http` and `https` are accepted.
+pub fn normalize_origin(input: &str) -> Result<(String, String), AppError> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Ok((default_scheme(), DEFAULT_HOST.to_string()));
+    }
+
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+
+    let url = reqwest::Url::parse(&with_scheme)
+        .map_err(|e| AppError::invalid_input(format!("Invalid host '{}': {}", input, e)))?;
+    let scheme = url.scheme().to_string();
+    if scheme != "http" && scheme != "https" {
+        return Err(AppError::invalid_input(format!(
+            "Unsupported scheme '{}' in host '{}'",
+            scheme, input
+        )));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| AppError::invalid_input(format!("Invalid host '{}'", input)))?
+        .to_lowercase();
+
+    if host == DEFAULT_HOST || host == "api.github.com" {
+        return Ok((default_scheme(), DEFAULT_HOST.to_string()));
+    }
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host,
+    };
+    Ok((scheme, host))
+}
+
+/// Settings stored for `username`, preferring the active account when the
+/// login exists on several hosts, and falling back to github.com
+pub fn account_config(username: &str, app_handle: &tauri::AppHandle) -> AccountConfig {
+    let Ok(index) = load_index(app_handle) else {
+        return AccountConfig::default();
+    };
+
+    index
+        .active_account()
+        .filter(|account| account.login.eq_ignore_ascii_case(username))
+        .or_else(|| {
+            index
+                .accounts
+                .iter()
+                .find(|account| account.login.eq_ignore_ascii_case(username))
+        })
+        .map(Account::config)
+        .unwrap_or_default()
+}
+
+/// Persist the settings for `username`
+pub fn set_account_config(
+    username: &str,
+    config: AccountConfig,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    update_index(app_handle, |index| {
+        index.upsert(
+            Account::new(config.provider, &config.host, username, None).with_scheme(&config.scheme),
+        );
+        Ok(())
+    })
+}
+
+pub fn load_index(app_handle: &tauri::AppHandle) -> Result<AccountIndex, AppError> {
+    Ok(settings::load(app_handle)?.accounts)
+}
+
+/// Change the index and save it, as one `settings::update`
+pub fn update_index<T>(
+    app_handle: &tauri::AppHandle,
+    change: impl FnOnce(&mut AccountIndex) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    settings::update(app_handle, |settings| change(&mut settings.accounts))
+}
+
+/// The index from `accounts.json`, where it was kept before the settings
+/// file existed, for migrating it there
+pub fn load_legacy_index(app_handle: &tauri::AppHandle) -> Result<AccountIndex, AppError> {
+    let path = get_accounts_path(app_handle)?;
+
+    if !path.exists() {
+        return Ok(AccountIndex::default());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let value: serde_json::Value = serde_json::from_str(&content)?;
+    if value.get("accounts").is_some() {
+        return serde_json::from_value(value).map_err(AppError::from);
+    }
+
+    // Before the index existed the file was a map of login to settings
+    let legacy: BTreeMap<String, AccountConfig> = serde_json::from_value(value)?;
+    Ok(AccountIndex {
+        active: None,
+        accounts: legacy
+            .into_iter()
+            .map(|(login, config)| {
+                Account::new(config.provider, &config.host, &login, None)
+                    .with_scheme(&config.scheme)
+            })
+            .collect(),
+    })
+}
+
+fn get_accounts_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::storage(format!("Failed to get app data dir: {}", e)))?;
+
+    Ok(app_data_dir.join("accounts.json"))
+}
@@ -0,0 +1,277 @@
+use super::CredentialStore;
+use crate::error::AppError;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File header, bumped if the layout ever changes
+const MAGIC: &[u8; 4] = b"GPC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+type Salt = [u8; SALT_LEN];
+
+/// Tokens kept in one file encrypted with ChaCha20-Poly1305, for machines
+/// without a usable OS keyring.
+///
+/// The key is derived with Argon2id from `secret` (a user passphrase or a
+/// machine-bound id) and a random salt stored in the file. The layout is
+/// `MAGIC | salt | nonce | ciphertext`, where the plaintext is a JSON map
+/// of `service/user` to token.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    secret: String,
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: PathBuf, secret: String) -> Self {
+        EncryptedFileStore { path, secret }
+    }
+
+    /// Check that the file, if present, decrypts with this secret
+    pub fn verify(&self) -> Result<(), AppError> {
+        self.read().map(|_| ())
+    }
+
+    /// Every stored `service/user` key and token
+    pub fn entries(&self) -> Result<BTreeMap<String, String>, AppError> {
+        self.read().map(|(_, entries)| entries)
+    }
+
+    /// Replace the whole file with `entries` under a fresh salt, as when the
+    /// secret changes
+    pub fn replace_all(&self, entries: &BTreeMap<String, String>) -> Result<(), AppError> {
+        self.write(&random_salt(), entries)
+    }
+
+    /// Decrypt the file. A missing file is an empty store with no salt yet.
+    fn read(&self) -> Result<(Option<Salt>, BTreeMap<String, String>), AppError> {
+        if !self.path.exists() {
+            return Ok((None, BTreeMap::new()));
+        }
+
+        let bytes = fs::read(&self.path)?;
+        let header_len = MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if bytes.len() < header_len || !bytes.starts_with(MAGIC) {
+            return Err(AppError::credentials("Credentials file is corrupt"));
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&bytes[MAGIC.len()..MAGIC.len() + SALT_LEN]);
+        let nonce = Nonce::from_slice(&bytes[MAGIC.len() + SALT_LEN..header_len]);
+
+        let cipher = ChaCha20Poly1305::new(&derive_key(&self.secret, &salt)?);
+        let plaintext = cipher.decrypt(nonce, &bytes[header_len..]).map_err(|_| {
+            AppError::credentials("Could not decrypt credentials (wrong passphrase?)")
+        })?;
+
+        let entries = serde_json::from_slice(&plaintext)?;
+        Ok((Some(salt), entries))
+    }
+
+    /// Encrypt `entries` under a new nonce and swap the file in atomically
+    fn write(&self, salt: &Salt, entries: &BTreeMap<String, String>) -> Result<(), AppError> {
+        let plaintext = serde_json::to_vec(entries)?;
+        let cipher = ChaCha20Poly1305::new(&derive_key(&self.secret, salt)?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_ref())
+            .map_err(|_| AppError::credentials("Failed to encrypt credentials"))?;
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(salt);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, bytes)?;
+        restrict_permissions(&temp_path)?;
+        fs::rename(&temp_path, &self.path).map_err(AppError::from)
+    }
+
+    /// Read, change and write back the entries, keeping the existing salt
+    fn update(&self, change: impl FnOnce(&mut BTreeMap<String, String>)) -> Result<(), AppError> {
+        let (salt, mut entries) = self.read()?;
+        change(&mut entries);
+        self.write(&salt.unwrap_or_else(random_salt), &entries)
+    }
+}
+
+impl CredentialStore for EncryptedFileStore {
+    fn save(&self, service: &str, user: &str, token: &str) -> Result<(), AppError> {
+        self.update(|entries| {
+            entries.insert(entry_key(service, user), token.to_string());
+        })
+    }
+
+    fn get(&self, service: &str, user: &str) -> Result<String, AppError> {
+        let (_, mut entries) = self.read()?;
+        entries
+            .remove(&entry_key(service, user))
+            .ok_or_else(|| AppError::credentials("No matching entry found in secure storage"))
+    }
+
+    fn delete(&self, service: &str, user: &str) -> Result<(), AppError> {
+        self.update(|entries| {
+            entries.remove(&entry_key(service, user));
+        })
+    }
+}
+
+/// Secret bound to this machine and user profile: the OS machine id combined
+/// with the app data path. It keeps the file unreadable when copied
+/// elsewhere, but not from other programs running as the same user.
+pub fn machine_secret(app_data_dir: &Path) -> Result<String, AppError> {
+    let machine_id = ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+        .ok_or_else(|| {
+            AppError::credentials("No machine id is available; use a passphrase instead")
+        })?;
+
+    Ok(format!("{}:{}", machine_id, app_data_dir.display()))
+}
+
+fn entry_key(service: &str, user: &str) -> String {
+    format!("{}/{}", service, user)
+}
+
+fn derive_key(secret: &str, salt: &[u8]) -> Result<Key, AppError> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(secret.as_bytes(), salt, &mut key)
+        .map_err(|e| AppError::credentials(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+fn random_salt() -> Salt {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> Result<(), AppError> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(AppError::from)
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> Result<(), AppError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh file path under the temp dir, removed with its directory on drop
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new() -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "gitpulse-credentials-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            TempFile(dir.join("credentials.bin"))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            if let Some(dir) = self.0.parent() {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+    }
+
+    #[test]
+    fn save_get_and_delete() {
+        let file = TempFile::new();
+        let store = EncryptedFileStore::new(file.0.clone(), "secret".to_string());
+
+        store.save("service", "alice", "token-a").unwrap();
+        store.save("service", "bob", "token-b").unwrap();
+        assert_eq!(store.get("service", "alice").unwrap(), "token-a");
+        assert_eq!(store.get("service", "bob").unwrap(), "token-b");
+
+        store.save("service", "alice", "token-a2").unwrap();
+        assert_eq!(store.get("service", "alice").unwrap(), "token-a2");
+
+        store.delete("service", "alice").unwrap();
+        assert_eq!(
+            store.get("service", "alice").unwrap_err().code(),
+            "CREDENTIALS"
+        );
+        assert_eq!(store.get("service", "bob").unwrap(), "token-b");
+
+        // The token never appears in the file as plain text
+        let bytes = fs::read(&file.0).unwrap();
+        assert!(bytes.starts_with(MAGIC));
+        assert!(!bytes.windows(7).any(|window| window == b"token-b"));
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_store() {
+        let file = TempFile::new();
+        let store = EncryptedFileStore::new(file.0.clone(), "secret".to_string());
+
+        store.verify().unwrap();
+        assert!(store.get("service", "alice").is_err());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let file = TempFile::new();
+        EncryptedFileStore::new(file.0.clone(), "right".to_string())
+            .save("service", "alice", "token-a")
+            .unwrap();
+
+        let wrong = EncryptedFileStore::new(file.0.clone(), "wrong".to_string());
+        assert_eq!(wrong.verify().unwrap_err().code(), "CREDENTIALS");
+        assert!(wrong.get("service", "alice").is_err());
+        assert!(wrong.save("service", "bob", "token-b").is_err());
+
+        // A failed write leaves the file readable with the right secret
+        let right = EncryptedFileStore::new(file.0.clone(), "right".to_string());
+        assert_eq!(right.get("service", "alice").unwrap(), "token-a");
+    }
+
+    #[test]
+    fn replace_all_rewrites_under_a_new_secret() {
+        let file = TempFile::new();
+        let old = EncryptedFileStore::new(file.0.clone(), "old".to_string());
+        old.save("service", "alice", "token-a").unwrap();
+        old.save("service", "bob", "token-b").unwrap();
+
+        let (_, entries) = old.read().unwrap();
+        let new = EncryptedFileStore::new(file.0.clone(), "new".to_string());
+        new.replace_all(&entries).unwrap();
+
+        assert!(old.verify().is_err());
+        assert_eq!(new.get("service", "alice").unwrap(), "token-a");
+        assert_eq!(new.get("service", "bob").unwrap(), "token-b");
+
+        let mut only_bob = BTreeMap::new();
+        only_bob.insert(entry_key("service", "bob"), "token-b2".to_string());
+        new.replace_all(&only_bob).unwrap();
+
+        assert!(new.get("service", "alice").is_err());
+        assert_eq!(new.get("service", "bob").unwrap(), "token-b2");
+    }
+}
@@ -0,0 +1,270 @@
+mod encrypted_file;
+mod os_keyring;
+
+pub use encrypted_file::EncryptedFileStore;
+pub use os_keyring::KeyringStore;
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::Manager;
+
+/// Somewhere tokens can be stored, looked up by service and user key
+pub trait CredentialStore {
+    fn save(&self, service: &str, user: &str, token: &str) -> Result<(), AppError>;
+    fn get(&self, service: &str, user: &str) -> Result<String, AppError>;
+    fn delete(&self, service: &str, user: &str) -> Result<(), AppError>;
+}
+
+/// Available credential backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackendKind {
+    Keyring,
+    EncryptedFile,
+}
+
+/// Where the encrypted file's key comes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeySource {
+    #[default]
+    Machine,
+    Passphrase,
+}
+
+/// The chosen backend, persisted as `credentials.json`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendConfig {
+    pub backend: BackendKind,
+    #[serde(default)]
+    pub key_source: KeySource,
+}
+
+/// Passphrase for the encrypted file, held in memory only for this session
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+static KEYRING_AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+/// Service name the keyring probe uses
+const PROBE_SERVICE: &str = "gitpulse";
+
+/// Keyring user key for a token on `host`. github.com keeps the bare key so
+/// tokens saved before host support are still found.
+pub fn host_key(key: &str, host: &str) -> String {
+    if host == crate::accounts::DEFAULT_HOST {
+        key.to_string()
+    } else {
+        format!("{}@{}", key, host)
+    }
+}
+
+/// Store a token. The first one saved also pins the automatic backend
+/// choice, since the keyring may work in some sessions (desktop) and not
+/// others (SSH, headless) and tokens must be looked up where they were put.
+pub fn save_token(
+    service: &str,
+    user: &str,
+    token: &str,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    current_store(app_handle)?.save(service, user, token)?;
+
+    if saved_backend_config(app_handle).is_none() {
+        set_backend_config(&backend_config(app_handle), app_handle)?;
+    }
+
+    Ok(())
+}
+
+pub fn get_token(
+    service: &str,
+    user: &str,
+    app_handle: &tauri::AppHandle,
+) -> Result<String, AppError> {
+    current_store(app_handle)?.get(service, user)
+}
+
+pub fn delete_token(
+    service: &str,
+    user: &str,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    current_store(app_handle)?.delete(service, user)
+}
+
+/// Whether the OS keyring works on this machine (checked once per run)
+pub fn keyring_available() -> bool {
+    *KEYRING_AVAILABLE.get_or_init(|| os_keyring::probe(PROBE_SERVICE))
+}
+
+/// The configured backend. Without a saved choice the keyring is used when
+/// it works and the machine-keyed file otherwise.
+pub fn backend_config(app_handle: &tauri::AppHandle) -> BackendConfig {
+    saved_backend_config(app_handle).unwrap_or_else(|| BackendConfig {
+        backend: if keyring_available() {
+            BackendKind::Keyring
+        } else {
+            BackendKind::EncryptedFile
+        },
+        key_source: KeySource::Machine,
+    })
+}
+
+fn saved_backend_config(app_handle: &tauri::AppHandle) -> Option<BackendConfig> {
+    get_config_path(app_handle)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// True when tokens are in a passphrase-protected file that hasn't been
+/// unlocked yet this session
+pub fn is_locked(config: &BackendConfig) -> bool {
+    config.backend == BackendKind::EncryptedFile
+        && config.key_source == KeySource::Passphrase
+        && session_passphrase().is_none()
+}
+
+/// Remember the passphrase for this session once it decrypts the file
+pub fn unlock(passphrase: &str, app_handle: &tauri::AppHandle) -> Result<(), AppError> {
+    file_store(KeySource::Passphrase, Some(passphrase), app_handle)?.verify()?;
+    set_session_passphrase(Some(passphrase.to_string()));
+    Ok(())
+}
+
+/// Move the tokens stored under `users` from the current backend to
+/// `target` and switch to it. `passphrase` is required when the target file
+/// is passphrase-protected. Re-keying the encrypted file keeps every entry
+/// in it, not only those under `users`. Returns how many tokens were moved.
+pub fn migrate_tokens(
+    service: &str,
+    users: &[String],
+    target: BackendConfig,
+    passphrase: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<usize, AppError> {
+    let current = backend_config(app_handle);
+    let source = current_store(app_handle)?;
+
+    let tokens: BTreeMap<&str, String> = users
+        .iter()
+        .filter_map(|user| {
+            source
+                .get(service, user)
+                .ok()
+                .map(|token| (user.as_str(), token))
+        })
+        .collect();
+
+    let mut moved = tokens.len();
+
+    if target.backend == BackendKind::EncryptedFile {
+        let destination = file_store(target.key_source, passphrase, app_handle)?;
+
+        if current.backend == BackendKind::EncryptedFile {
+            // Same file under a new key: rewrite all of it in one go
+            let entries = file_store(current.key_source, None, app_handle)?.entries()?;
+            moved = entries.len();
+            destination.replace_all(&entries)?;
+        } else {
+            for (user, token) in &tokens {
+                destination.save(service, user, token)?;
+            }
+        }
+    } else {
+        for (user, token) in &tokens {
+            KeyringStore.save(service, user, token)?;
+        }
+    }
+
+    set_backend_config(&target, app_handle)?;
+    if target.key_source == KeySource::Passphrase {
+        set_session_passphrase(passphrase.map(str::to_string));
+    }
+
+    // Old copies are only removed once the new backend holds every token
+    if current.backend != target.backend {
+        for user in tokens.keys() {
+            let _ = source.delete(service, user);
+        }
+    }
+
+    Ok(moved)
+}
+
+fn current_store(app_handle: &tauri::AppHandle) -> Result<Box<dyn CredentialStore>, AppError> {
+    let config = backend_config(app_handle);
+    match config.backend {
+        BackendKind::Keyring => Ok(Box::new(KeyringStore)),
+        BackendKind::EncryptedFile => {
+            Ok(Box::new(file_store(config.key_source, None, app_handle)?))
+        }
+    }
+}
+
+/// The encrypted file keyed from `key_source`. For a passphrase, the given
+/// one is used, or else the one unlocked this session.
+fn file_store(
+    key_source: KeySource,
+    passphrase: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<EncryptedFileStore, AppError> {
+    let app_data_dir = get_app_data_dir(app_handle)?;
+
+    let secret = match key_source {
+        KeySource::Machine => encrypted_file::machine_secret(&app_data_dir)?,
+        KeySource::Passphrase => passphrase
+            .map(str::to_string)
+            .or_else(session_passphrase)
+            .filter(|passphrase| !passphrase.is_empty())
+            .ok_or(AppError::CredentialsLocked)?,
+    };
+
+    Ok(EncryptedFileStore::new(
+        app_data_dir.join("credentials.bin"),
+        secret,
+    ))
+}
+
+fn session_passphrase() -> Option<String> {
+    PASSPHRASE
+        .lock()
+        .ok()
+        .and_then(|passphrase| passphrase.clone())
+}
+
+fn set_session_passphrase(passphrase: Option<String>) {
+    if let Ok(mut current) = PASSPHRASE.lock() {
+        *current = passphrase;
+    }
+}
+
+fn set_backend_config(
+    config: &BackendConfig,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    let path = get_config_path(app_handle)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(config)?;
+    fs::write(&path, json).map_err(AppError::from)
+}
+
+fn get_config_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    Ok(get_app_data_dir(app_handle)?.join("credentials.json"))
+}
+
+fn get_app_data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::storage(format!("Failed to get app data dir: {}", e)))
+}
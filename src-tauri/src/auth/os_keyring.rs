@@ -0,0 +1,33 @@
+use super::CredentialStore;
+use crate::error::AppError;
+use keyring::Entry;
+
+/// Tokens kept in the OS keyring (Keychain, Credential Manager, Secret Service)
+pub struct KeyringStore;
+
+impl CredentialStore for KeyringStore {
+    fn save(&self, service: &str, user: &str, token: &str) -> Result<(), AppError> {
+        let entry = Entry::new(service, user)?;
+        entry.set_password(token).map_err(AppError::from)
+    }
+
+    fn get(&self, service: &str, user: &str) -> Result<String, AppError> {
+        let entry = Entry::new(service, user)?;
+        entry.get_password().map_err(AppError::from)
+    }
+
+    fn delete(&self, service: &str, user: &str) -> Result<(), AppError> {
+        let entry = Entry::new(service, user)?;
+        entry.delete_password().map_err(AppError::from)
+    }
+}
+
+/// Whether the platform keyring can be reached. A missing entry still means
+/// the keyring works; anything else (no Secret Service on the session bus,
+/// locked storage, ...) means it can't be used.
+pub fn probe(service: &str) -> bool {
+    match Entry::new(service, "__probe__").and_then(|entry| entry.get_password()) {
+        Ok(_) | Err(keyring::Error::NoEntry) => true,
+        Err(_) => false,
+    }
+}
@@ -1,225 +1,1680 @@
+use crate::accounts::{self, Account, AccountConfig, AccountIndex, DEFAULT_HOST};
+use crate::auth::{self, BackendConfig, BackendKind, KeySource};
+use crate::device_flow::{DeviceCode, DeviceFlowClient};
+use crate::error::AppError;
+use crate::export::{ExportFormat, ExportRow};
+use crate::history::{HistoryChange, HistoryDay};
+use crate::local_git::LocalContributions;
+use crate::providers::{self, github, ProviderKind};
+use crate::rate_limit::RateLimitStatus;
+use crate::render::{PngOptions, RenderOptions, RenderTheme};
+use crate::retry::{AttemptCount, RetryPolicy};
+use crate::settings::{self, Settings, SettingsUpdate, Theme};
+use crate::stats::ContributionStats;
+use chrono::{DateTime, Local, Months, NaiveDate, NaiveDateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
-use tauri::Manager;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ContributionDay {
-    date: String,
+    pub date: String,
     #[serde(rename = "contributionCount")]
-    contribution_count: i32,
+    pub contribution_count: i32,
+    /// Color level from 0 (none) to 4, per the configured `LevelScale`
+    #[serde(rename = "contributionLevel", default)]
+    pub contribution_level: Option<u8>,
+    /// Hex color of the level
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+impl ContributionDay {
+    /// A day whose level is not known yet
+    pub fn new(date: String, contribution_count: i32) -> Self {
+        ContributionDay {
+            date,
+            contribution_count,
+            contribution_level: None,
+            color: None,
+        }
+    }
+}
+
+/// Per-day contribution counts split by contribution type
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContributionBreakdown {
+    pub date: String,
+    pub commits: i32,
+    pub pull_requests: i32,
+    pub issues: i32,
+    pub reviews: i32,
+}
+
+/// Contributions to a single repository, with counts for active days only
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepositoryContributions {
+    pub name_with_owner: String,
+    pub is_private: bool,
+    pub primary_language: Option<String>,
+    pub total_count: i32,
+    pub days: Vec<ContributionDay>,
+}
+
+/// Repositories contributed to within a range, busiest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepositoryList {
+    pub repositories: Vec<RepositoryContributions>,
+    /// Whether GitHub left repositories out: it lists at most 100 per
+    /// contribution type and year, so the least active ones can be missing
+    pub truncated: bool,
+}
+
+/// One source for `fetch_merged_contributions`. Unset fields fall back to
+/// the settings stored for the account, as in `fetch_contributions`, and the
+/// token always comes from the keyring.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountProfile {
+    pub username: String,
+    pub provider: Option<ProviderKind>,
+    pub host: Option<String>,
+    /// Name shown for this source, `username@host` by default
+    pub label: Option<String>,
+}
+
+/// Summed calendar of several sources
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedContributions {
+    pub days: Vec<ContributionDay>,
+    /// For each date with activity, the count contributed by each source label
+    pub by_source: BTreeMap<String, BTreeMap<String, i32>>,
+    pub failures: Vec<SourceFailure>,
+}
+
+/// A source that could not be fetched while merging
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceFailure {
+    pub label: String,
+    pub error: AppError,
 }
 
 #[derive(Debug, Serialize)]
-pub struct FetchResult {
+#[serde(rename_all = "camelCase")]
+pub struct FetchResult<T = Vec<ContributionDay>> {
     ok: bool,
-    data: Option<Vec<ContributionDay>>,
-    error: Option<String>,
+    data: Option<T>,
+    error: Option<AppError>,
+    /// Requests sent for this result (zero when served from the cache)
+    #[serde(flatten)]
+    attempts: AttemptCount,
+    /// Whether `data` is an expired cache entry
+    stale: bool,
+    /// Seconds since `data` was cached; `None` when it was just fetched
+    age: Option<u64>,
+    /// Cache keys being refreshed in the background; each reports back
+    /// through a `cache-refreshed` event
+    refreshing: Vec<String>,
 }
 
-/// Fetch GitHub contributions with filesystem caching
+impl<T> FetchResult<T> {
+    fn fetched(data: T, attempts: AttemptCount) -> Self {
+        FetchResult {
+            ok: true,
+            data: Some(data),
+            error: None,
+            attempts,
+            stale: false,
+            age: None,
+            refreshing: Vec::new(),
+        }
+    }
+
+    fn cached(data: T, age: u64) -> Self {
+        FetchResult {
+            age: Some(age),
+            ..FetchResult::fetched(data, AttemptCount::default())
+        }
+    }
+
+    fn failed(error: AppError, attempts: AttemptCount) -> Self {
+        FetchResult {
+            ok: false,
+            data: None,
+            error: Some(error),
+            attempts,
+            stale: false,
+            age: None,
+            refreshing: Vec::new(),
+        }
+    }
+}
+
+/// Payload of the `cache-refreshed` event sent when a background refresh of
+/// a stale cache entry finishes. On failure the stale entry stays cached.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheRefreshed<T> {
+    cache_key: String,
+    #[serde(flatten)]
+    result: FetchResult<T>,
+}
+
+/// Cache keys with a background refresh in flight
+static REFRESHING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Fetch contributions from the account's provider with filesystem caching.
+/// Without a username the active account is used; the token is read from the
+/// keyring and never passes through the webview.
 #[tauri::command]
 pub async fn fetch_contributions(
+    username: Option<String>,
+    host: Option<String>,
+    provider: Option<ProviderKind>,
+    app_handle: tauri::AppHandle,
+) -> Result<FetchResult, AppError> {
+    let username = resolve_username(username, &app_handle)?;
+    let source = resolve_source(&username, provider, host.as_deref(), &app_handle)?;
+    let token = resolve_token(&username, source.provider, &source.host, &app_handle);
+
+    let result = fetch_account_contributions(username, source, token, &app_handle).await;
+    Ok(with_level_scale(result, &app_handle))
+}
+
+/// Fetch one account's trailing-year calendar through the cache
+async fn fetch_account_contributions(
+    username: String,
+    source: AccountConfig,
+    token: Option<String>,
+    app_handle: &tauri::AppHandle,
+) -> FetchResult {
+    let key = cache_key(&username, &source.host);
+    let fetch = fetch_and_record(source, username, token, None, app_handle.clone());
+    fetch_with_cache(&key, app_handle, fetch).await
+}
+
+/// Fetch contribution days and add them to the history database. History
+/// is best effort: failing to store it doesn't fail the fetch.
+async fn fetch_and_record(
+    source: AccountConfig,
     username: String,
     token: Option<String>,
+    window: Option<(NaiveDate, NaiveDate)>,
     app_handle: tauri::AppHandle,
-) -> Result<FetchResult, String> {
-    // Try to load from cache first
-    if let Ok(cached_data) = load_from_cache(&username, &app_handle).await {
-        return Ok(FetchResult {
-            ok: true,
-            data: Some(cached_data),
-            error: None,
-        });
+) -> Result<Vec<ContributionDay>, AppError> {
+    let days = providers::fetch_contributions(&source, &username, token.as_deref(), window).await?;
+    let account = Account::new(source.provider, &source.host, &username, None).id;
+
+    // SQLite writes are blocking disk IO
+    let recorded = days.clone();
+    let stored = tokio::task::spawn_blocking(move || {
+        crate::history::record(&account, &recorded, &app_handle)
+    })
+    .await
+    .unwrap_or_else(|e| Err(AppError::storage(format!("History update failed: {}", e))));
+    if let Err(e) = stored {
+        eprintln!("Failed to record contribution history: {}", e);
     }
 
-    // Fetch from GitHub API
-    match fetch_from_github(&username, token.as_deref()).await {
-        Ok(days) => {
-            // Save to cache
-            let _ = save_to_cache(&username, &days, &app_handle).await;
-            
-            Ok(FetchResult {
-                ok: true,
-                data: Some(days),
-                error: None,
-            })
+    Ok(days)
+}
+
+/// Payload of the `contributions-updated` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContributionsUpdate {
+    pub username: String,
+    pub host: String,
+    pub days: Vec<ContributionDay>,
+}
+
+/// Fetch the active account's trailing-year calendar, bypassing the cache,
+/// and cache it. Returns the new calendar only if it differs from the cached one.
+pub(crate) async fn refresh_active_account(
+    app_handle: &tauri::AppHandle,
+) -> Result<Option<ContributionsUpdate>, AppError> {
+    let username = resolve_username(None, app_handle)?;
+    let source = resolve_source(&username, None, None, app_handle)?;
+    let token = resolve_token(&username, source.provider, &source.host, app_handle);
+    let key = cache_key(&username, &source.host);
+    let host = source.host.clone();
+
+    let mut days =
+        fetch_and_record(source, username.clone(), token, None, app_handle.clone()).await?;
+    let previous = load_from_cache::<Vec<ContributionDay>>(&key, app_handle).await;
+    save_to_cache(&key, &days, app_handle).await?;
+
+    if matches!(previous, Ok((cached, _)) if cached == days) {
+        return Ok(None);
+    }
+
+    apply_level_scale(&mut days, app_handle);
+
+    Ok(Some(ContributionsUpdate {
+        username,
+        host,
+        days,
+    }))
+}
+
+/// Fetch one merge source, returning its label alongside the outcome
+async fn fetch_profile(
+    profile: &AccountProfile,
+    app_handle: &tauri::AppHandle,
+) -> (String, FetchResult) {
+    let source = resolve_source(
+        &profile.username,
+        profile.provider,
+        profile.host.as_deref(),
+        app_handle,
+    );
+    let source = match source {
+        Ok(source) => source,
+        Err(e) => {
+            let label = profile
+                .label
+                .clone()
+                .unwrap_or_else(|| profile.username.clone());
+            return (label, FetchResult::failed(e, AttemptCount::default()));
         }
-        Err(e) => Ok(FetchResult {
-            ok: false,
-            data: None,
-            error: Some(e),
+    };
+
+    let label = profile
+        .label
+        .clone()
+        .unwrap_or_else(|| format!("{}@{}", profile.username, source.host));
+    let token = resolve_token(&profile.username, source.provider, &source.host, app_handle);
+    let result =
+        fetch_account_contributions(profile.username.clone(), source, token, app_handle).await;
+
+    (label, result)
+}
+
+/// Fetch several accounts (possibly on different providers) and sum them
+/// into one calendar. A failing source doesn't fail the others; it is listed
+/// in `failures`, and the result is only an error if every source failed.
+/// The result is stale if any source came from an expired cache entry.
+#[tauri::command]
+pub async fn fetch_merged_contributions(
+    profiles: Vec<AccountProfile>,
+    app_handle: tauri::AppHandle,
+) -> Result<FetchResult<MergedContributions>, AppError> {
+    if profiles.is_empty() {
+        return Err(AppError::invalid_input("At least one account is required"));
+    }
+
+    let fetches = profiles
+        .iter()
+        .map(|profile| fetch_profile(profile, &app_handle));
+    let (results, attempts) =
+        crate::retry::track_attempts(futures::future::join_all(fetches)).await;
+
+    let mut totals: BTreeMap<String, i32> = BTreeMap::new();
+    let mut by_source: BTreeMap<String, BTreeMap<String, i32>> = BTreeMap::new();
+    let mut failures = Vec::new();
+    let mut stale = false;
+    let mut age = None;
+    let mut refreshing = Vec::new();
+
+    for (label, result) in results {
+        stale |= result.stale;
+        age = age.max(result.age);
+        refreshing.extend(result.refreshing);
+
+        match result.data {
+            Some(days) => {
+                for day in days {
+                    *totals.entry(day.date.clone()).or_insert(0) += day.contribution_count;
+                    if day.contribution_count > 0 {
+                        *by_source
+                            .entry(day.date)
+                            .or_default()
+                            .entry(label.clone())
+                            .or_insert(0) += day.contribution_count;
+                    }
+                }
+            }
+            None => failures.push(SourceFailure {
+                label,
+                error: result.error.unwrap_or_else(AppError::invalid_response),
+            }),
+        }
+    }
+
+    // With every source failing, report the first failure's error
+    if failures.len() == profiles.len() {
+        let error = failures.into_iter().next().map(|failure| failure.error);
+        return Ok(FetchResult::failed(
+            error.unwrap_or_else(AppError::invalid_response),
+            attempts,
+        ));
+    }
+
+    let mut days: Vec<ContributionDay> = totals
+        .into_iter()
+        .map(|(date, contribution_count)| ContributionDay::new(date, contribution_count))
+        .collect();
+    apply_level_scale(&mut days, &app_handle);
+
+    let merged = MergedContributions {
+        days,
+        by_source,
+        failures,
+    };
+    Ok(FetchResult {
+        stale,
+        age,
+        refreshing,
+        ..FetchResult::fetched(merged, attempts)
+    })
+}
+
+/// Fetch contributions for an inclusive `from`..`to` date range (YYYY-MM-DD)
+#[tauri::command]
+pub async fn fetch_contributions_range(
+    username: Option<String>,
+    host: Option<String>,
+    provider: Option<ProviderKind>,
+    from: String,
+    to: String,
+    app_handle: tauri::AppHandle,
+) -> Result<FetchResult, AppError> {
+    let username = resolve_username(username, &app_handle)?;
+    let source = resolve_source(&username, provider, host.as_deref(), &app_handle)?;
+    let token = resolve_token(&username, source.provider, &source.host, &app_handle);
+    let (from, to) = parse_date_range(&from, &to)?;
+    let cache_key = format!("{}_{}_{}", cache_key(&username, &source.host), from, to);
+
+    let window = Some((from, to));
+    let fetch = fetch_and_record(source, username, token, window, app_handle.clone());
+    let result = fetch_with_cache(&cache_key, &app_handle, fetch).await;
+    Ok(with_level_scale(result, &app_handle))
+}
+
+/// Fetch per-day contribution counts split into commits, pull requests,
+/// issues and reviews. Defaults to the trailing year when no range is given.
+#[tauri::command]
+pub async fn fetch_contribution_breakdown(
+    username: Option<String>,
+    host: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<FetchResult<Vec<ContributionBreakdown>>, AppError> {
+    let username = resolve_username(username, &app_handle)?;
+    let host = resolve_github_host(&username, host.as_deref(), &app_handle)?;
+    let token = resolve_token(&username, ProviderKind::GitHub, &host, &app_handle);
+    let (from, to) = resolve_date_range(from.as_deref(), to.as_deref())?;
+    let cache_key = format!("{}_{}_{}_breakdown", cache_key(&username, &host), from, to);
+
+    Ok(fetch_with_cache(&cache_key, &app_handle, async move {
+        github::fetch_breakdown_from_github(&username, &host, token.as_deref(), from, to).await
+    })
+    .await)
+}
+
+/// Fetch contributions grouped by repository (commits, pull requests, issues
+/// and reviews). Defaults to the trailing year when no range is given.
+#[tauri::command]
+pub async fn fetch_repository_contributions(
+    username: Option<String>,
+    host: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<FetchResult<RepositoryList>, AppError> {
+    let username = resolve_username(username, &app_handle)?;
+    let host = resolve_github_host(&username, host.as_deref(), &app_handle)?;
+    let token = resolve_token(&username, ProviderKind::GitHub, &host, &app_handle);
+    let (from, to) = resolve_date_range(from.as_deref(), to.as_deref())?;
+    let cache_key = format!(
+        "{}_{}_{}_repositories",
+        cache_key(&username, &host),
+        from,
+        to
+    );
+
+    Ok(fetch_with_cache(&cache_key, &app_handle, async move {
+        github::fetch_repositories_from_github(&username, &host, token.as_deref(), from, to).await
+    })
+    .await)
+}
+
+/// Count commits authored by any of `emails` in the git repositories found
+/// under `directories`. Works offline and without a token, so private and
+/// air-gapped repositories show up too. Defaults to the heatmap's trailing year.
+/// Repositories that can't be read are skipped and listed in `failures`.
+#[tauri::command]
+pub async fn fetch_local_contributions(
+    directories: Vec<String>,
+    emails: Vec<String>,
+    from: Option<String>,
+    to: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<FetchResult<LocalContributions>, AppError> {
+    let (from, to) = match (from, to) {
+        (None, None) => providers::default_window(),
+        (from, to) => resolve_date_range(from.as_deref(), to.as_deref())?,
+    };
+    let directories: Vec<PathBuf> = directories.iter().map(PathBuf::from).collect();
+    let cache_key = local_cache_key(&directories, &emails, from, to);
+
+    let mut result = fetch_with_cache(&cache_key, &app_handle, async move {
+        // Walking history is blocking disk IO
+        tokio::task::spawn_blocking(move || {
+            crate::local_git::scan_contributions(&directories, &emails, from, to)
+        })
+        .await
+        .map_err(|e| AppError::storage(format!("Repository scan failed: {}", e)))?
+    })
+    .await;
+    if let Some(local) = result.data.as_mut() {
+        apply_level_scale(&mut local.days, &app_handle);
+    }
+    Ok(result)
+}
+
+/// Level calendar days with the configured `LevelScale`. Caches keep the
+/// provider's quartile levels, so changing the scale needs no refetch.
+fn apply_level_scale(days: &mut [ContributionDay], app_handle: &tauri::AppHandle) {
+    let settings = settings::load(app_handle).unwrap_or_default();
+    crate::levels::apply(days, settings.level_scale, &settings.level_thresholds);
+}
+
+fn with_level_scale(mut result: FetchResult, app_handle: &tauri::AppHandle) -> FetchResult {
+    if let Some(days) = result.data.as_mut() {
+        apply_level_scale(days, app_handle);
+    }
+    result
+}
+
+/// Stored daily counts of an account, kept across fetches beyond the
+/// trailing year. A missing `from` or `to` leaves that end unbounded.
+#[tauri::command]
+pub async fn fetch_contribution_history(
+    username: Option<String>,
+    host: Option<String>,
+    provider: Option<ProviderKind>,
+    from: Option<String>,
+    to: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<HistoryDay>, AppError> {
+    let account = resolve_account_id(username, provider, host.as_deref(), &app_handle)?;
+    let from = from.as_deref().map(parse_date).transpose()?;
+    let to = to.as_deref().map(parse_date).transpose()?;
+
+    crate::history::query_days(&account, from, to, &app_handle)
+}
+
+/// Past days whose counts changed after they were first fetched, such as
+/// contributions GitHub attributed or removed retroactively
+#[tauri::command]
+pub async fn fetch_history_changes(
+    username: Option<String>,
+    host: Option<String>,
+    provider: Option<ProviderKind>,
+    from: Option<String>,
+    to: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<HistoryChange>, AppError> {
+    let account = resolve_account_id(username, provider, host.as_deref(), &app_handle)?;
+    let from = from.as_deref().map(parse_date).transpose()?;
+    let to = to.as_deref().map(parse_date).transpose()?;
+
+    crate::history::query_changes(&account, from, to, &app_handle)
+}
+
+/// History key of an account, resolved like a fetch's source
+fn resolve_account_id(
+    username: Option<String>,
+    provider: Option<ProviderKind>,
+    host: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<String, AppError> {
+    let username = resolve_username(username, app_handle)?;
+    let source = resolve_source(&username, provider, host, app_handle)?;
+    Ok(Account::new(source.provider, &source.host, &username, None).id)
+}
+
+/// Streaks, peaks and averages of `days`. `today` (YYYY-MM-DD) is the
+/// user's current date and defaults to the local date; weeks start on the
+/// configured weekday.
+#[tauri::command]
+pub async fn compute_contribution_stats(
+    days: Vec<ContributionDay>,
+    today: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<ContributionStats, AppError> {
+    let today = match today {
+        Some(today) => parse_date(&today)?,
+        None => Local::now().date_naive(),
+    };
+    let week_start = settings::load(&app_handle)
+        .map(|settings| settings.week_start)
+        .unwrap_or_default();
+
+    crate::stats::compute(&days, today, week_start)
+}
+
+/// Render `days` as a standalone SVG for embedding in READMEs and wikis.
+/// The theme defaults to the app's (light when it follows the system), and
+/// `show_stats` adds a line of streaks and totals as of `today`.
+#[tauri::command]
+pub async fn render_heatmap_svg(
+    days: Vec<ContributionDay>,
+    theme: Option<RenderTheme>,
+    title: Option<String>,
+    show_stats: Option<bool>,
+    today: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let (options, stats) = render_options(
+        &days,
+        theme,
+        title,
+        show_stats,
+        today.as_deref(),
+        &app_handle,
+    )?;
+    Ok(crate::render::render_svg(&days, &options, stats.as_ref()))
+}
+
+/// Render `days` to a PNG at `scale` (1 = one pixel per SVG unit) and write
+/// it to `path`, returning the path written. Options are as for
+/// `render_heatmap_svg`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_heatmap_png(
+    days: Vec<ContributionDay>,
+    path: String,
+    scale: Option<f32>,
+    theme: Option<RenderTheme>,
+    title: Option<String>,
+    show_stats: Option<bool>,
+    today: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let (options, stats) = render_options(
+        &days,
+        theme,
+        title,
+        show_stats,
+        today.as_deref(),
+        &app_handle,
+    )?;
+    let png_options = PngOptions {
+        scale: scale.unwrap_or(1.0),
+        ..PngOptions::default()
+    };
+
+    // Font loading and rasterizing are blocking work
+    let png = tokio::task::spawn_blocking(move || {
+        crate::render::render_png(&days, &options, stats.as_ref(), &png_options)
+    })
+    .await
+    .map_err(|e| AppError::storage(format!("Rendering failed: {}", e)))??;
+
+    let path = PathBuf::from(path);
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, png)?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Render options from command arguments, defaulting to the app's theme and
+/// week start, plus the stats line's data when requested
+fn render_options(
+    days: &[ContributionDay],
+    theme: Option<RenderTheme>,
+    title: Option<String>,
+    show_stats: Option<bool>,
+    today: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<(RenderOptions, Option<ContributionStats>), AppError> {
+    let settings = settings::load(app_handle).unwrap_or_default();
+    let options = RenderOptions {
+        theme: theme.unwrap_or(match settings.theme {
+            Theme::Dark => RenderTheme::Dark,
+            Theme::Light | Theme::System => RenderTheme::Light,
         }),
+        week_start: settings.week_start,
+        title,
+    };
+
+    let stats = if show_stats.unwrap_or(false) {
+        let today = match today {
+            Some(today) => parse_date(today)?,
+            None => Local::now().date_naive(),
+        };
+        Some(crate::stats::compute(days, today, settings.week_start)?)
+    } else {
+        None
+    };
+
+    Ok((options, stats))
+}
+
+/// Cache key for a local scan, derived from its directories and emails
+fn local_cache_key(
+    directories: &[PathBuf],
+    emails: &[String],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> String {
+    let mut directories = directories.to_vec();
+    directories.sort();
+    let mut emails: Vec<String> = emails.iter().map(|e| e.trim().to_lowercase()).collect();
+    emails.sort();
+
+    let mut hasher = DefaultHasher::new();
+    directories.hash(&mut hasher);
+    emails.hash(&mut hasher);
+
+    format!("local_{:016x}_{}_{}", hasher.finish(), from, to)
+}
+
+/// The given username, or the login of the active account
+fn resolve_username(
+    username: Option<String>,
+    app_handle: &tauri::AppHandle,
+) -> Result<String, AppError> {
+    if let Some(username) = username.filter(|u| !u.trim().is_empty()) {
+        return Ok(username);
     }
+
+    accounts::load_index(app_handle)?
+        .active_account()
+        .map(|account| account.login.clone())
+        .ok_or(AppError::NotSignedIn)
 }
 
-/// Fetch data from GitHub GraphQL API
-async fn fetch_from_github(
+/// Token for an account from the credential store: its own key in the account index,
+/// or for GitHub the per-host key used before accounts existed
+fn resolve_token(
     username: &str,
-    token: Option<&str>,
-) -> Result<Vec<ContributionDay>, String> {
-    let query = r#"
-        query($login: String!) {
-            user(login: $login) {
-                contributionsCollection {
-                    contributionCalendar {
-                        weeks {
-                            contributionDays {
-                                date
-                                contributionCount
-                            }
-                        }
-                    }
-                }
+    provider: ProviderKind,
+    host: &str,
+    app_handle: &tauri::AppHandle,
+) -> Option<String> {
+    let id = accounts::load_index(app_handle)
+        .ok()
+        .and_then(|index| {
+            index
+                .accounts
+                .into_iter()
+                .find(|account| {
+                    account.provider == provider
+                        && account.host == host
+                        && account.login.eq_ignore_ascii_case(username)
+                })
+                .map(|account| account.id)
+        })
+        .unwrap_or_else(|| Account::new(provider, host, username, None).id);
+
+    auth::get_token(SERVICE_NAME, &id, app_handle)
+        .ok()
+        .or_else(|| {
+            if provider == ProviderKind::GitHub {
+                auth::get_token(SERVICE_NAME, &auth::host_key(USER_KEY, host), app_handle).ok()
+            } else {
+                None
+            }
+        })
+}
+
+/// Provider, host and scheme for a fetch: explicit arguments win, then the
+/// settings stored for the account, then the provider's public host
+fn resolve_source(
+    username: &str,
+    provider: Option<ProviderKind>,
+    host: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<AccountConfig, AppError> {
+    let stored = accounts::account_config(username, app_handle);
+    let provider = provider.unwrap_or(stored.provider);
+
+    match host {
+        Some(input) if !input.trim().is_empty() => {
+            let mut config = AccountConfig::parse(provider, input)?;
+            // A bare host keeps the scheme stored for it
+            if !input.contains("://") && provider == stored.provider && config.host == stored.host {
+                config.scheme = stored.scheme;
             }
+            Ok(config)
         }
-    "#;
+        _ if provider == stored.provider => Ok(stored),
+        _ => Ok(AccountConfig::public(provider)),
+    }
+}
 
-    let client = reqwest::Client::new();
-    let mut request = client
-        .post("https://api.github.com/graphql")
-        .header("Content-Type", "application/json")
-        .header("User-Agent", "github-widget");
+/// Like `resolve_source`, for commands that only exist on GitHub
+fn resolve_github_host(
+    username: &str,
+    host: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<String, AppError> {
+    match resolve_source(username, None, host, app_handle)? {
+        source if source.provider == ProviderKind::GitHub => Ok(source.host),
+        source => Err(AppError::Unsupported {
+            provider: source.provider.as_str().to_string(),
+        }),
+    }
+}
 
-    // Add authorization if token provided
-    if let Some(t) = token {
-        request = request.header("Authorization", format!("Bearer {}", t));
+/// Cache key for an account. github.com keeps the bare username so existing
+/// cache files stay valid.
+fn cache_key(username: &str, host: &str) -> String {
+    if host == DEFAULT_HOST {
+        username.to_string()
+    } else {
+        format!("{}@{}", username, host.replace(':', "_"))
     }
+}
 
-    let body = serde_json::json!({
-        "query": query,
-        "variables": {
-            "login": username
+/// Serve `cache_key` from the cache, or run `fetch` and cache its result.
+/// An expired entry is served right away, flagged as stale, while `fetch`
+/// refreshes it in the background (see `refresh_in_background`).
+async fn fetch_with_cache<T, F>(
+    cache_key: &str,
+    app_handle: &tauri::AppHandle,
+    fetch: F,
+) -> FetchResult<T>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    F: Future<Output = Result<T, AppError>> + Send + 'static,
+{
+    let ttl = settings::load(app_handle)
+        .map(|settings| settings.cache_ttl_secs)
+        .unwrap_or_else(|_| Settings::default().cache_ttl_secs);
+
+    match load_from_cache::<T>(cache_key, app_handle).await {
+        Ok((cached_data, age)) if age <= ttl => {
+            return FetchResult::cached(cached_data, age);
         }
-    });
+        Ok((stale_data, age)) => {
+            let refreshing = refresh_in_background(cache_key, app_handle, fetch);
+            return FetchResult {
+                stale: true,
+                refreshing: refreshing.into_iter().collect(),
+                ..FetchResult::cached(stale_data, age)
+            };
+        }
+        Err(_) => {}
+    }
 
-    let response = request
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    let (result, attempts) = crate::retry::track_attempts(fetch).await;
+    match result {
+        Ok(data) => {
+            let _ = save_to_cache(cache_key, &data, app_handle).await;
+            FetchResult::fetched(data, attempts)
+        }
+        Err(e) => FetchResult::failed(e, attempts),
+    }
+}
 
-    if !response.status().is_success() {
-        return Err(format!("GitHub API error: {}", response.status()));
+/// Run `fetch` on its own task, cache what it returns and emit
+/// `cache-refreshed` with the outcome. Returns the key being refreshed, or
+/// `None` if a refresh of it was already in flight.
+fn refresh_in_background<T, F>(
+    cache_key: &str,
+    app_handle: &tauri::AppHandle,
+    fetch: F,
+) -> Option<String>
+where
+    T: Serialize + Send + 'static,
+    F: Future<Output = Result<T, AppError>> + Send + 'static,
+{
+    let started = REFRESHING
+        .lock()
+        .map(|mut refreshing| refreshing.insert(cache_key.to_string()))
+        .unwrap_or(false);
+    if !started {
+        return None;
     }
 
-    let result: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("JSON parse error: {}", e))?;
-
-    // Check for GraphQL errors
-    if let Some(errors) = result.get("errors") {
-        return Err(format!("GraphQL error: {}", errors));
-    }
-
-    // Extract and flatten contribution days
-    let weeks = result["data"]["user"]["contributionsCollection"]["contributionCalendar"]["weeks"]
-        .as_array()
-        .ok_or("Invalid response structure")?;
-
-    let mut days = Vec::new();
-    for week in weeks {
-        if let Some(contribution_days) = week["contributionDays"].as_array() {
-            for day in contribution_days {
-                days.push(ContributionDay {
-                    date: day["date"].as_str().unwrap_or("").to_string(),
-                    contribution_count: day["contributionCount"].as_i64().unwrap_or(0) as i32,
-                });
+    let cache_key = cache_key.to_string();
+    let app_handle = app_handle.clone();
+    let key = cache_key.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let (result, attempts) = crate::retry::track_attempts(fetch).await;
+        let result = match result {
+            Ok(data) => {
+                let _ = save_to_cache(&cache_key, &data, &app_handle).await;
+                FetchResult::fetched(data, attempts)
             }
+            Err(e) => FetchResult::failed(e, attempts),
+        };
+
+        if let Ok(mut refreshing) = REFRESHING.lock() {
+            refreshing.remove(&cache_key);
+        }
+
+        // Emitted as JSON so payloads of any `T` need not be `Clone`
+        let event = CacheRefreshed { cache_key, result };
+        if let Ok(payload) = serde_json::to_value(&event) {
+            let _ = app_handle.emit("cache-refreshed", payload);
         }
+    });
+
+    Some(key)
+}
+
+/// Parse a YYYY-MM-DD date
+fn parse_date(value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| AppError::invalid_input(format!("Invalid date '{}': {}", value, e)))
+}
+
+/// Parse and validate an inclusive date range
+fn parse_date_range(from: &str, to: &str) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let from = parse_date(from)?;
+    let to = parse_date(to)?;
+
+    if from > to {
+        return Err(AppError::invalid_input(format!(
+            "Start date {} is after end date {}",
+            from, to
+        )));
     }
 
-    Ok(days)
+    Ok((from, to))
 }
 
-/// Load contributions from cache file
-async fn load_from_cache(
+/// Like `parse_date_range`, but a missing end defaults to today and a
+/// missing start to one year before the end
+fn resolve_date_range(
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let to = match to {
+        Some(to) => parse_date(to)?,
+        None => Local::now().date_naive(),
+    };
+    let from = match from {
+        Some(from) => parse_date(from)?,
+        None => to
+            .checked_sub_months(Months::new(12))
+            .and_then(|d| d.succ_opt())
+            .unwrap_or(to),
+    };
+
+    if from > to {
+        return Err(AppError::invalid_input(format!(
+            "Start date {} is after end date {}",
+            from, to
+        )));
+    }
+
+    Ok((from, to))
+}
+
+/// Load cached data from cache file, along with its age in seconds
+async fn load_from_cache<T: DeserializeOwned>(
     username: &str,
     app_handle: &tauri::AppHandle,
-) -> Result<Vec<ContributionDay>, String> {
+) -> Result<(T, u64), AppError> {
     let cache_path = get_cache_path(username, app_handle)?;
 
     if !cache_path.exists() {
-        return Err("Cache file not found".to_string());
+        return Err(AppError::storage("Cache file not found"));
     }
 
-    // Check if cache is older than 5 minutes
-    let metadata = fs::metadata(&cache_path).map_err(|e| e.to_string())?;
-    if let Ok(modified) = metadata.modified() {
-        if let Ok(elapsed) = modified.elapsed() {
-            if elapsed.as_secs() > 300 {
-                // Cache expired (5 minutes)
-                return Err("Cache expired".to_string());
-            }
-        }
-    }
+    let age = fs::metadata(&cache_path)?
+        .modified()
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+
+    let content = fs::read_to_string(&cache_path)?;
+    let data = serde_json::from_str(&content)?;
 
-    let content = fs::read_to_string(&cache_path).map_err(|e| e.to_string())?;
-    serde_json::from_str(&content).map_err(|e| e.to_string())
+    Ok((data, age))
 }
 
-/// Save contributions to cache file
-async fn save_to_cache(
+/// Save data to cache file
+async fn save_to_cache<T: Serialize + ?Sized>(
     username: &str,
-    days: &[ContributionDay],
+    days: &T,
     app_handle: &tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let cache_path = get_cache_path(username, app_handle)?;
 
     // Ensure cache directory exists
     if let Some(parent) = cache_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        fs::create_dir_all(parent)?;
     }
 
-    let json = serde_json::to_string_pretty(&days).map_err(|e| e.to_string())?;
-    fs::write(&cache_path, json).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&days)?;
+    fs::write(&cache_path, json)?;
 
     Ok(())
 }
 
 /// Get cache file path for a specific username
-fn get_cache_path(username: &str, app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+fn get_cache_path(username: &str, app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+        .map_err(|e| AppError::storage(format!("Failed to get app data dir: {}", e)))?;
 
     Ok(app_data_dir.join("cache").join(format!("{}_contributions.json", username)))
 }
 
+/// Remove every cache file written for one account: its trailing-year
+/// calendar and any range, breakdown or repository views
+fn clear_account_cache(
+    username: &str,
+    host: &str,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::storage(format!("Failed to get app data dir: {}", e)))?;
+
+    let cache_dir = app_data_dir.join("cache");
+    if !cache_dir.exists() {
+        return Ok(());
+    }
+
+    let key = cache_key(username, host);
+    for entry in fs::read_dir(&cache_dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_account_cache_file(&name, &key) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `name` is `{key}_contributions.json` or a dated view of it,
+/// `{key}_YYYY-MM-DD_...`, without matching logins that merely share a prefix
+fn is_account_cache_file(name: &str, key: &str) -> bool {
+    let Some(rest) = name.strip_prefix(key).and_then(|r| r.strip_prefix('_')) else {
+        return false;
+    };
+
+    rest == "contributions.json"
+        || rest
+            .get(..11)
+            .and_then(|date| date.strip_suffix('_'))
+            .is_some_and(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+}
+
 /// Clear all cached data
 #[tauri::command]
-pub async fn clear_cache(app_handle: tauri::AppHandle) -> Result<String, String> {
+pub async fn clear_cache(app_handle: tauri::AppHandle) -> Result<String, AppError> {
     let app_data_dir = app_handle
         .path()
         .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+        .map_err(|e| AppError::storage(format!("Failed to get app data dir: {}", e)))?;
 
     let cache_dir = app_data_dir.join("cache");
 
     if cache_dir.exists() {
-        fs::remove_dir_all(&cache_dir).map_err(|e| e.to_string())?;
-        fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+        fs::remove_dir_all(&cache_dir)?;
+        fs::create_dir_all(&cache_dir)?;
     }
 
     Ok("Cache cleared successfully".to_string())
 }
 
+/// Where an export was written and how many days it holds
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    pub rows: usize,
+}
+
+/// Write an account's contributions within `from`..`to` (the trailing year
+/// by default) to `path` as CSV, JSON, NDJSON or iCalendar. Days come from
+/// the history database, filled in from the cached calendar, with breakdown
+/// columns when the breakdown for that range is cached.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_contributions(
+    username: Option<String>,
+    host: Option<String>,
+    provider: Option<ProviderKind>,
+    from: Option<String>,
+    to: Option<String>,
+    format: ExportFormat,
+    path: String,
+    app_handle: tauri::AppHandle,
+) -> Result<ExportSummary, AppError> {
+    let username = resolve_username(username, &app_handle)?;
+    let source = resolve_source(&username, provider, host.as_deref(), &app_handle)?;
+    let (from, to) = resolve_date_range(from.as_deref(), to.as_deref())?;
+    let account = Account::new(source.provider, &source.host, &username, None).id;
+    let key = cache_key(&username, &source.host);
+    let (first, last) = (from.to_string(), to.to_string());
+    let in_range = |date: &str| date >= first.as_str() && date <= last.as_str();
+
+    let mut days: BTreeMap<String, ContributionDay> = BTreeMap::new();
+    if let Ok((cached, _)) = load_from_cache::<Vec<ContributionDay>>(&key, &app_handle).await {
+        for day in cached.into_iter().filter(|day| in_range(&day.date)) {
+            days.insert(day.date.clone(), day);
+        }
+    }
+    for day in crate::history::query_days(&account, Some(from), Some(to), &app_handle)? {
+        let stored = ContributionDay::new(day.date.clone(), day.contribution_count);
+        days.insert(day.date, stored);
+    }
+
+    let mut days: Vec<ContributionDay> = days.into_values().collect();
+    apply_level_scale(&mut days, &app_handle);
+
+    let breakdown_key = format!("{}_{}_{}_breakdown", key, from, to);
+    let breakdown: BTreeMap<String, ContributionBreakdown> =
+        load_from_cache::<Vec<ContributionBreakdown>>(&breakdown_key, &app_handle)
+            .await
+            .map(|(breakdown, _)| {
+                breakdown
+                    .into_iter()
+                    .map(|day| (day.date.clone(), day))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+    let rows: Vec<ExportRow> = days
+        .into_iter()
+        .map(|day| {
+            let split = breakdown.get(&day.date);
+            ExportRow {
+                contribution_count: day.contribution_count,
+                contribution_level: day.contribution_level,
+                commits: split.map(|split| split.commits),
+                pull_requests: split.map(|split| split.pull_requests),
+                issues: split.map(|split| split.issues),
+                reviews: split.map(|split| split.reviews),
+                date: day.date,
+            }
+        })
+        .collect();
+
+    let content = crate::export::write(&rows, format, &account, Utc::now())?;
+    let path = PathBuf::from(path);
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+
+    Ok(ExportSummary {
+        path: path.to_string_lossy().into_owned(),
+        rows: rows.len(),
+    })
+}
+
+/// Seconds between background refreshes of the active account
+#[tauri::command]
+pub async fn get_refresh_interval() -> u64 {
+    crate::scheduler::interval()
+}
+
+/// Change the background refresh interval (at least 60 seconds)
+#[tauri::command]
+pub async fn set_refresh_interval(
+    seconds: u64,
+    app_handle: tauri::AppHandle,
+) -> Result<u64, AppError> {
+    settings::update(&app_handle, |settings| {
+        settings.refresh_interval_secs = seconds;
+        Ok(())
+    })?;
+    Ok(crate::scheduler::interval())
+}
+
+/// The persisted settings, including the account index
+#[tauri::command]
+pub async fn get_settings(app_handle: tauri::AppHandle) -> Result<Settings, AppError> {
+    settings::load(&app_handle)
+}
+
+/// Validate and save changed preferences, returning the new settings. Emits
+/// `settings-changed`.
+#[tauri::command]
+pub async fn update_settings(
+    update: SettingsUpdate,
+    app_handle: tauri::AppHandle,
+) -> Result<Settings, AppError> {
+    settings::update(&app_handle, |settings| {
+        update.apply(settings);
+        Ok(settings.clone())
+    })
+}
+
+/// Current retry policy for transient network failures
+#[tauri::command]
+pub async fn get_retry_policy() -> RetryPolicy {
+    crate::retry::policy()
+}
+
+/// Change how transient failures are retried
+#[tauri::command]
+pub async fn set_retry_policy(policy: RetryPolicy) -> RetryPolicy {
+    crate::retry::set_policy(policy);
+    crate::retry::policy()
+}
+
+/// Stop every fetch in flight or waiting to retry; they fail with `CANCELLED`
+#[tauri::command]
+pub async fn cancel_pending_fetches() {
+    crate::retry::cancel_pending();
+}
+
+/// API budgets seen so far for each host and token, with when they reset
+#[tauri::command]
+pub async fn get_rate_limit_status() -> Vec<RateLimitStatus> {
+    crate::rate_limit::snapshot()
+}
+
 const SERVICE_NAME: &str = "gitpulse";
 const USER_KEY: &str = "github_token";
 
+/// Keyring key for the token of `host` (github.com when omitted)
+fn token_key(host: Option<&str>) -> Result<String, AppError> {
+    let host = accounts::normalize_host(host.unwrap_or(DEFAULT_HOST))?;
+    Ok(auth::host_key(USER_KEY, &host))
+}
+
+#[tauri::command]
+pub async fn save_github_token(
+    token: String,
+    host: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    auth::save_token(
+        SERVICE_NAME,
+        &token_key(host.as_deref())?,
+        &token,
+        &app_handle,
+    )
+}
+
+/// Whether an account has a token, and for GitHub its scopes and expiry.
+/// The token itself is never returned.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenStatus {
+    pub account: Option<Account>,
+    pub has_token: bool,
+    pub scopes: Option<Vec<String>>,
+    pub expires_at: Option<String>,
+}
+
+/// Report on the token of account `id`, or of the active account
+#[tauri::command]
+pub async fn get_token_status(
+    id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<TokenStatus, AppError> {
+    let index = accounts::load_index(&app_handle)?;
+    let account = match id {
+        Some(id) => index.find(&id).cloned(),
+        None => index.active_account().cloned(),
+    };
+
+    let Some(account) = account else {
+        return Ok(TokenStatus {
+            account: None,
+            has_token: false,
+            scopes: None,
+            expires_at: None,
+        });
+    };
+
+    let token = resolve_token(&account.login, account.provider, &account.host, &app_handle);
+    let info = match (&token, account.provider) {
+        (Some(token), ProviderKind::GitHub) => fetch_token_info(&account.host, token).await.ok(),
+        _ => None,
+    };
+
+    Ok(TokenStatus {
+        has_token: token.is_some(),
+        scopes: info.as_ref().and_then(|info| info.scopes.clone()),
+        expires_at: info.and_then(|info| info.expires_at),
+        account: Some(account),
+    })
+}
+
+#[tauri::command]
+pub async fn delete_github_token(
+    host: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    auth::delete_token(SERVICE_NAME, &token_key(host.as_deref())?, &app_handle)
+}
+
+/// Which credential backend holds tokens, and whether it must be unlocked
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialBackendStatus {
+    pub backend: BackendKind,
+    pub key_source: KeySource,
+    pub keyring_available: bool,
+    pub locked: bool,
+}
+
 #[tauri::command]
-pub async fn save_github_token(token: String) -> Result<(), String> {
-    crate::auth::save_token(SERVICE_NAME, USER_KEY, &token)
+pub async fn get_credential_backend(
+    app_handle: tauri::AppHandle,
+) -> Result<CredentialBackendStatus, AppError> {
+    let config = auth::backend_config(&app_handle);
+    Ok(CredentialBackendStatus {
+        backend: config.backend,
+        key_source: config.key_source,
+        keyring_available: auth::keyring_available(),
+        locked: auth::is_locked(&config),
+    })
 }
 
+/// Unlock a passphrase-protected credentials file for this session
 #[tauri::command]
-pub async fn get_github_token() -> Result<String, String> {
-    crate::auth::get_token(SERVICE_NAME, USER_KEY)
+pub async fn unlock_credentials(
+    passphrase: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    auth::unlock(&passphrase, &app_handle)
 }
 
+/// Move every stored token (for indexed accounts and the legacy per-host
+/// keys) to another backend and use it from now on. Returns the number of
+/// tokens moved.
 #[tauri::command]
-pub async fn delete_github_token() -> Result<(), String> {
-    crate::auth::delete_token(SERVICE_NAME, USER_KEY)
+pub async fn migrate_credentials(
+    backend: BackendKind,
+    key_source: Option<KeySource>,
+    passphrase: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, AppError> {
+    if backend == BackendKind::Keyring && !auth::keyring_available() {
+        return Err(AppError::credentials(
+            "The OS keyring is not available on this machine",
+        ));
+    }
+
+    let index = accounts::load_index(&app_handle)?;
+    let mut users = vec![USER_KEY.to_string()];
+    for account in &index.accounts {
+        users.push(account.id.clone());
+        if account.provider == ProviderKind::GitHub {
+            users.push(auth::host_key(USER_KEY, &account.host));
+        }
+    }
+    users.sort();
+    users.dedup();
+
+    let target = BackendConfig {
+        backend,
+        key_source: key_source.unwrap_or_default(),
+    };
+    auth::migrate_tokens(
+        SERVICE_NAME,
+        &users,
+        target,
+        passphrase.as_deref(),
+        &app_handle,
+    )
+}
+
+/// Scopes the widget needs; `user` includes `read:user`
+const REQUIRED_SCOPE: &str = "read:user";
+
+/// Scopes that grant far more than reading a contribution calendar
+const EXCESSIVE_SCOPES: [&str; 4] = ["repo", "delete_repo", "admin:org", "workflow"];
+
+/// Warn about tokens expiring within this many days
+const EXPIRY_WARNING_DAYS: i64 = 7;
+
+/// Result of checking a candidate token before it is saved
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenValidation {
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    /// Granted scopes; `None` for fine-grained tokens, which have none
+    pub scopes: Option<Vec<String>>,
+    /// RFC 3339 expiry, when the token has one
+    pub expires_at: Option<String>,
+    /// Seconds left until `expires_at`, negative once expired
+    pub expires_in: Option<i64>,
+    pub warnings: Vec<String>,
+}
+
+/// Check a token against `/user` on the given host before it is saved,
+/// reporting its owner, scopes and expiry along with any warnings
+#[tauri::command]
+pub async fn validate_github_token(
+    token: String,
+    host: Option<String>,
+) -> Result<TokenValidation, AppError> {
+    let host = accounts::normalize_host(host.as_deref().unwrap_or(DEFAULT_HOST))?;
+    let info = fetch_token_info(&host, token.trim()).await?;
+
+    let expires_in = info
+        .expires_at
+        .as_deref()
+        .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+        .map(|expires_at| (expires_at.with_timezone(&Utc) - Utc::now()).num_seconds());
+    let warnings = token_warnings(info.scopes.as_deref(), expires_in);
+
+    Ok(TokenValidation {
+        login: info.login,
+        name: info.name,
+        avatar_url: info.avatar_url,
+        scopes: info.scopes,
+        expires_at: info.expires_at,
+        expires_in,
+        warnings,
+    })
+}
+
+/// Warnings for a token with the given scopes and time left
+fn token_warnings(scopes: Option<&[String]>, expires_in: Option<i64>) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    // Fine-grained tokens report no scopes, so only classic ones are checked
+    if let Some(scopes) = scopes {
+        let has = |scope: &str| scopes.iter().any(|s| s == scope);
+
+        if !has(REQUIRED_SCOPE) && !has("user") {
+            warnings.push(format!(
+                "Token is missing the '{}' scope; some contributions may not be visible",
+                REQUIRED_SCOPE
+            ));
+        }
+
+        let excessive: Vec<&str> = EXCESSIVE_SCOPES
+            .iter()
+            .copied()
+            .filter(|scope| has(*scope))
+            .collect();
+        if !excessive.is_empty() {
+            warnings.push(format!(
+                "Token has more access than needed ({}); '{}' is enough",
+                excessive.join(", "),
+                REQUIRED_SCOPE
+            ));
+        }
+    }
+
+    match expires_in {
+        Some(seconds) if seconds <= 0 => warnings.push("Token has expired".to_string()),
+        Some(seconds) if seconds < EXPIRY_WARNING_DAYS * 86_400 => {
+            let days = seconds / 86_400;
+            warnings.push(match days {
+                0 => format!("Token expires in {} hours", (seconds / 3600).max(1)),
+                1 => "Token expires in 1 day".to_string(),
+                days => format!("Token expires in {} days", days),
+            });
+        }
+        _ => {}
+    }
+
+    warnings
+}
+
+/// What `/user` reveals about a GitHub token
+struct TokenInfo {
+    login: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
+    /// From `X-OAuth-Scopes`; absent for fine-grained tokens
+    scopes: Option<Vec<String>>,
+    /// From `github-authentication-token-expiration`, as RFC 3339
+    expires_at: Option<String>,
+}
+
+/// Look up the user and token metadata for a GitHub token
+async fn fetch_token_info(host: &str, token: &str) -> Result<TokenInfo, AppError> {
+    let client = crate::retry::client();
+    let response = crate::retry::send(|| {
+        client
+            .get(format!("{}/user", github::rest_url(host)))
+            .header("User-Agent", "github-widget")
+            .header("Authorization", format!("Bearer {}", token))
+    })
+    .await?;
+
+    crate::rate_limit::record_headers(
+        host,
+        crate::rate_limit::CORE,
+        Some(token),
+        response.status().as_u16(),
+        response.headers(),
+    );
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response("GitHub", &response));
+    }
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let scopes = header("x-oauth-scopes").map(|scopes| {
+        scopes
+            .split(',')
+            .map(|scope| scope.trim().to_string())
+            .filter(|scope| !scope.is_empty())
+            .collect()
+    });
+    let expires_at = header("github-authentication-token-expiration")
+        .map(|expires_at| parse_token_expiration(&expires_at).unwrap_or(expires_at));
+
+    let user: serde_json::Value = response.json().await?;
+
+    let login = user["login"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(AppError::invalid_response)?;
+
+    Ok(TokenInfo {
+        login,
+        name: user["name"].as_str().map(str::to_string),
+        avatar_url: user["avatar_url"].as_str().map(str::to_string),
+        scopes,
+        expires_at,
+    })
+}
+
+/// Convert GitHub's `2024-05-01 12:00:00 UTC` (or `... -0700`) expiry to
+/// RFC 3339
+fn parse_token_expiration(value: &str) -> Option<String> {
+    let value = value.trim();
+    let expires_at = DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S %z")
+        .map(|expires_at| expires_at.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(
+                value.trim_end_matches("UTC").trim_end(),
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .map(|expires_at| expires_at.and_utc())
+        })
+        .ok()?;
+
+    Some(expires_at.to_rfc3339())
+}
+
+/// Store the provider and host (github.com, a GitHub Enterprise Server,
+/// gitlab.com, ...) for an account. A `http://` prefix on `host` is kept for
+/// GitLab and Gitea instances served without TLS.
+#[tauri::command]
+pub async fn set_account_host(
+    username: String,
+    host: String,
+    provider: Option<ProviderKind>,
+    app_handle: tauri::AppHandle,
+) -> Result<AccountConfig, AppError> {
+    let provider = provider.unwrap_or_default();
+    let config = if host.trim().is_empty() {
+        AccountConfig::public(provider)
+    } else {
+        AccountConfig::parse(provider, &host)?
+    };
+
+    accounts::set_account_config(&username, config.clone(), &app_handle)?;
+    Ok(config)
+}
+
+#[tauri::command]
+pub async fn get_account_config(username: String, app_handle: tauri::AppHandle) -> AccountConfig {
+    accounts::account_config(&username, &app_handle)
+}
+
+/// List the stored accounts and which one is active
+#[tauri::command]
+pub async fn list_accounts(app_handle: tauri::AppHandle) -> Result<AccountIndex, AppError> {
+    accounts::load_index(&app_handle)
+}
+
+/// Add (or update) an account and store its token in the keyring under the
+/// account's own key. The first account added becomes the active one.
+#[tauri::command]
+pub async fn add_account(
+    login: String,
+    provider: Option<ProviderKind>,
+    host: Option<String>,
+    label: Option<String>,
+    token: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Account, AppError> {
+    let provider = provider.unwrap_or_default();
+    let config = match host.as_deref() {
+        Some(host) if !host.trim().is_empty() => AccountConfig::parse(provider, host)?,
+        _ => AccountConfig::public(provider),
+    };
+    let account = Account::new(provider, &config.host, &login, label).with_scheme(&config.scheme);
+
+    register_account(&account, token.as_deref(), false, &app_handle)?;
+    Ok(account)
+}
+
+/// Save the account's token (if any) under its own keyring key and add it to
+/// the index, making it active when asked to or when nothing else is
+fn register_account(
+    account: &Account,
+    token: Option<&str>,
+    activate: bool,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    if let Some(token) = token {
+        auth::save_token(SERVICE_NAME, &account.id, token, app_handle)?;
+    }
+
+    accounts::update_index(app_handle, |index| {
+        index.upsert(account.clone());
+        if activate || index.active_account().is_none() {
+            index.active = Some(account.id.clone());
+        }
+        Ok(())
+    })
+}
+
+/// Make the account with `id` the active one
+#[tauri::command]
+pub async fn switch_account(id: String, app_handle: tauri::AppHandle) -> Result<Account, AppError> {
+    accounts::update_index(&app_handle, |index| {
+        let account = index.find(&id).cloned().ok_or_else(|| AppError::NotFound {
+            message: format!("Unknown account '{}'", id),
+        })?;
+
+        index.active = Some(account.id.clone());
+        Ok(account)
+    })
+}
+
+/// Remove an account together with its keyring token and cached data
+#[tauri::command]
+pub async fn remove_account(id: String, app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    let account = accounts::update_index(&app_handle, |index| {
+        index.remove(&id).ok_or_else(|| AppError::NotFound {
+            message: format!("Unknown account '{}'", id),
+        })
+    })?;
+
+    // Accounts added without a token have no stored entry
+    let _ = auth::delete_token(SERVICE_NAME, &account.id, &app_handle);
+
+    clear_account_cache(&account.login, &account.host, &app_handle)
+}
+
+/// OAuth app client id baked in at build time; `client_id` arguments override it
+const GITHUB_CLIENT_ID: Option<&str> = option_env!("GITPULSE_GITHUB_CLIENT_ID");
+
+/// Fails with reason `not_configured` when the build has no client id and
+/// none was passed, so the UI can fall back to pasting a token
+fn device_flow_client(host: &str, client_id: Option<&str>) -> Result<DeviceFlowClient, AppError> {
+    let client_id = client_id
+        .or(GITHUB_CLIENT_ID)
+        .ok_or_else(|| AppError::LoginFailed {
+            reason: "not_configured".to_string(),
+            message: "Browser sign-in needs a GitHub OAuth app client id, and none is configured"
+                .to_string(),
+        })?;
+    Ok(DeviceFlowClient::new(&github::web_url(host), client_id))
+}
+
+/// Start a GitHub device-flow login. The UI shows `userCode` and opens
+/// `verificationUri`, then calls `complete_device_login` with the result.
+#[tauri::command]
+pub async fn start_device_login(
+    host: Option<String>,
+    client_id: Option<String>,
+) -> Result<DeviceCode, AppError> {
+    let host = accounts::normalize_host(host.as_deref().unwrap_or(DEFAULT_HOST))?;
+    device_flow_client(&host, client_id.as_deref())?
+        .request_code(REQUIRED_SCOPE)
+        .await
+}
+
+/// Wait for the user to approve a device-flow login, then store the token in
+/// the keyring and make the account active
+#[tauri::command]
+pub async fn complete_device_login(
+    device_code: DeviceCode,
+    host: Option<String>,
+    client_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Account, AppError> {
+    let host = accounts::normalize_host(host.as_deref().unwrap_or(DEFAULT_HOST))?;
+    let token = device_flow_client(&host, client_id.as_deref())?
+        .poll_for_token(
+            &device_code.device_code,
+            device_code.interval,
+            device_code.expires_in,
+        )
+        .await?;
+
+    let login = fetch_token_info(&host, &token).await?.login;
+    let account = Account::new(ProviderKind::GitHub, &host, &login, None);
+
+    register_account(&account, Some(&token), true, &app_handle)?;
+    Ok(account)
 }
@@ -0,0 +1,343 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Extra wait GitHub asks for on each `slow_down` response
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+
+/// Device and user codes issued by `/login/device/code`, as exchanged with
+/// the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    pub interval: u64,
+}
+
+/// `/login/device/code` response body, in GitHub's snake_case
+#[derive(Debug, Deserialize)]
+struct DeviceCodeResponse {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+impl From<DeviceCodeResponse> for DeviceCode {
+    fn from(response: DeviceCodeResponse) -> Self {
+        DeviceCode {
+            device_code: response.device_code,
+            user_code: response.user_code,
+            verification_uri: response.verification_uri,
+            expires_in: response.expires_in,
+            interval: response.interval,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+    interval: Option<u64>,
+}
+
+/// GitHub OAuth device authorization flow against one web host.
+///
+/// `base_url` is the web root (`https://github.com`, or a GitHub Enterprise
+/// Server), so a local mock of the two OAuth endpoints can stand in for it.
+pub struct DeviceFlowClient {
+    base_url: String,
+    client_id: String,
+    http: reqwest::Client,
+}
+
+impl DeviceFlowClient {
+    pub fn new(base_url: &str, client_id: &str) -> Self {
+        DeviceFlowClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client_id: client_id.to_string(),
+            http: crate::retry::client(),
+        }
+    }
+
+    /// Request a device code and the user code to show in the UI
+    pub async fn request_code(&self, scope: &str) -> Result<DeviceCode, AppError> {
+        let response = self
+            .http
+            .post(format!("{}/login/device/code", self.base_url))
+            .header("Accept", "application/json")
+            .header("User-Agent", "github-widget")
+            .form(&[("client_id", self.client_id.as_str()), ("scope", scope)])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::from_response("GitHub OAuth", &response));
+        }
+
+        let code: DeviceCodeResponse = response.json().await?;
+
+        Ok(code.into())
+    }
+
+    /// Poll for the access token every `interval` seconds until the user
+    /// approves, denies, or the code expires. `slow_down` responses lengthen
+    /// the interval for all later polls, as the spec requires.
+    pub async fn poll_for_token(
+        &self,
+        device_code: &str,
+        interval: u64,
+        expires_in: u64,
+    ) -> Result<String, AppError> {
+        let mut interval = Duration::from_secs(interval.max(1));
+        let deadline = Instant::now() + Duration::from_secs(expires_in);
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            if Instant::now() >= deadline {
+                return Err(AppError::LoginFailed {
+                    reason: "expired_token".to_string(),
+                    message: "The device code expired before it was approved".to_string(),
+                });
+            }
+
+            let response = self
+                .http
+                .post(format!("{}/login/oauth/access_token", self.base_url))
+                .header("Accept", "application/json")
+                .header("User-Agent", "github-widget")
+                .form(&[
+                    ("client_id", self.client_id.as_str()),
+                    ("device_code", device_code),
+                    ("grant_type", DEVICE_GRANT_TYPE),
+                ])
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(AppError::from_response("GitHub OAuth", &response));
+            }
+
+            let result: TokenResponse = response.json().await?;
+
+            if let Some(token) = result.access_token {
+                return Ok(token);
+            }
+
+            match result.error.as_deref() {
+                Some("authorization_pending") => {}
+                Some("slow_down") => {
+                    interval = result
+                        .interval
+                        .map(Duration::from_secs)
+                        .unwrap_or(interval + SLOW_DOWN_STEP);
+                }
+                Some("expired_token") => {
+                    return Err(AppError::LoginFailed {
+                        reason: "expired_token".to_string(),
+                        message: "The device code expired before it was approved".to_string(),
+                    });
+                }
+                Some("access_denied") => {
+                    return Err(AppError::LoginFailed {
+                        reason: "access_denied".to_string(),
+                        message: "Authorization was denied".to_string(),
+                    });
+                }
+                Some(error) => {
+                    return Err(AppError::LoginFailed {
+                        reason: error.to_string(),
+                        message: format!(
+                            "GitHub OAuth error: {} {}",
+                            error,
+                            result.error_description.unwrap_or_default()
+                        ),
+                    });
+                }
+                None => return Err(AppError::invalid_response()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// A request the mock received: when it arrived and its form body
+    struct Received {
+        at: Instant,
+        body: String,
+    }
+
+    /// Serve `responses` in order, one per request, as JSON bodies on a local
+    /// listener standing in for the OAuth endpoints
+    async fn mock(responses: Vec<&'static str>) -> (String, Arc<Mutex<Vec<Received>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+
+        tokio::spawn(async move {
+            for body in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut stream).await;
+                log.lock().unwrap().push(Received {
+                    at: Instant::now(),
+                    body: request,
+                });
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.ok();
+            }
+        });
+
+        (base_url, received)
+    }
+
+    /// Read one request and return its body
+    async fn read_request(stream: &mut TcpStream) -> String {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 1024];
+
+        loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            buffer.extend_from_slice(&chunk[..read]);
+
+            let text = String::from_utf8_lossy(&buffer);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text[..end]
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+
+                if buffer.len() >= end + 4 + length || read == 0 {
+                    return String::from_utf8_lossy(&buffer[end + 4..]).into_owned();
+                }
+            } else if read == 0 {
+                return String::new();
+            }
+        }
+    }
+
+    /// A client without the shared timeouts: with the clock paused, their
+    /// timers would move it forward while the mock is answering
+    fn test_client(base_url: &str) -> DeviceFlowClient {
+        DeviceFlowClient {
+            http: reqwest::Client::new(),
+            ..DeviceFlowClient::new(base_url, "client-id")
+        }
+    }
+
+    fn reason(error: AppError) -> String {
+        match error {
+            AppError::LoginFailed { reason, .. } => reason,
+            other => panic!("expected LoginFailed, got {:?}", other),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn polls_through_pending_and_slow_down_until_approved() {
+        let (base_url, received) = mock(vec![
+            r#"{"error":"authorization_pending"}"#,
+            r#"{"error":"slow_down","interval":3}"#,
+            r#"{"error":"slow_down"}"#,
+            r#"{"access_token":"gho_token","token_type":"bearer","scope":"read:user"}"#,
+        ])
+        .await;
+        let client = test_client(&base_url);
+
+        let start = Instant::now();
+        let token = client.poll_for_token("device-code", 1, 900).await.unwrap();
+        assert_eq!(token, "gho_token");
+
+        let received = received.lock().unwrap();
+        let offsets: Vec<u64> = received
+            .iter()
+            .map(|request| (request.at - start).as_secs())
+            .collect();
+        // 1s, then 1s again after pending, 3s from the server's interval,
+        // then 3s + SLOW_DOWN_STEP when slow_down has no interval
+        assert_eq!(offsets, vec![1, 2, 5, 13]);
+
+        let body = &received[0].body;
+        assert!(body.contains("client_id=client-id"));
+        assert!(body.contains("device_code=device-code"));
+        assert!(body.contains("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn access_denied_fails_the_login() {
+        let (base_url, received) = mock(vec![
+            r#"{"error":"authorization_pending"}"#,
+            r#"{"error":"access_denied","error_description":"The user has denied your application access."}"#,
+        ])
+        .await;
+        let client = test_client(&base_url);
+
+        let error = client
+            .poll_for_token("device-code", 5, 900)
+            .await
+            .unwrap_err();
+
+        assert_eq!(reason(error), "access_denied");
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expired_token_fails_the_login() {
+        let (base_url, _) = mock(vec![r#"{"error":"expired_token"}"#]).await;
+        let client = test_client(&base_url);
+
+        let error = client
+            .poll_for_token("device-code", 5, 900)
+            .await
+            .unwrap_err();
+
+        assert_eq!(reason(error), "expired_token");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_polling_once_the_code_expires() {
+        let (base_url, received) = mock(vec![
+            r#"{"error":"authorization_pending"}"#,
+            r#"{"error":"authorization_pending"}"#,
+            r#"{"error":"authorization_pending"}"#,
+        ])
+        .await;
+        let client = test_client(&base_url);
+
+        let start = Instant::now();
+        let error = client
+            .poll_for_token("device-code", 5, 12)
+            .await
+            .unwrap_err();
+
+        assert_eq!(reason(error), "expired_token");
+        // Polls at 5s and 10s; the wake-up at 15s is past the deadline
+        assert_eq!(received.lock().unwrap().len(), 2);
+        assert_eq!((Instant::now() - start).as_secs(), 15);
+    }
+}
@@ -0,0 +1,250 @@
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::fmt;
+
+/// Errors returned by every command. They reach the UI as
+/// `{ code, message, ...fields }`, where `code` is stable per variant so the
+/// frontend can branch on it and `message` is only meant for display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// The host could not be reached (no connection, DNS failure, timeout)
+    Offline { message: String },
+    /// The token was rejected or lacks access to the resource
+    Unauthorized { status: u16 },
+    /// The requested user, account or resource does not exist
+    NotFound { message: String },
+    /// An API rate limit was hit; `reset_at` is when it lifts (Unix seconds)
+    RateLimited { reset_at: Option<i64> },
+    /// Any other unsuccessful HTTP response
+    Http { service: String, status: u16 },
+    /// The GraphQL API answered with errors; `error_type` is GitHub's
+    /// `type` field, such as `NOT_FOUND` or `FORBIDDEN`
+    GraphQl {
+        error_type: Option<String>,
+        message: String,
+    },
+    /// The response could not be parsed or had an unexpected shape
+    InvalidResponse { message: String },
+    /// An argument was invalid (malformed date, host, missing value, ...)
+    InvalidInput { message: String },
+    /// No account is signed in
+    NotSignedIn,
+    /// The feature doesn't exist for this account's provider
+    Unsupported { provider: String },
+    /// A device-flow login did not complete; `reason` is the OAuth error
+    /// code, such as `expired_token` or `access_denied`
+    LoginFailed { reason: String, message: String },
+    /// The credential store could not be read or written
+    Credentials { message: String },
+    /// The encrypted credentials file needs its passphrase first
+    CredentialsLocked,
+    /// Local files (cache, settings, repositories) could not be used
+    Storage { message: String },
+    /// The request was cancelled while waiting to retry
+    Cancelled,
+}
+
+impl AppError {
+    /// Stable identifier of the variant, sent to the UI as `code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Offline { .. } => "OFFLINE",
+            AppError::Unauthorized { .. } => "UNAUTHORIZED",
+            AppError::NotFound { .. } => "NOT_FOUND",
+            AppError::RateLimited { .. } => "RATE_LIMITED",
+            AppError::Http { .. } => "HTTP",
+            AppError::GraphQl { .. } => "GRAPHQL",
+            AppError::InvalidResponse { .. } => "INVALID_RESPONSE",
+            AppError::InvalidInput { .. } => "INVALID_INPUT",
+            AppError::NotSignedIn => "NOT_SIGNED_IN",
+            AppError::Unsupported { .. } => "UNSUPPORTED",
+            AppError::LoginFailed { .. } => "LOGIN_FAILED",
+            AppError::Credentials { .. } => "CREDENTIALS",
+            AppError::CredentialsLocked => "CREDENTIALS_LOCKED",
+            AppError::Storage { .. } => "STORAGE",
+            AppError::Cancelled => "CANCELLED",
+        }
+    }
+
+    /// Classify an unsuccessful response from `service` ("GitHub", "GitLab",
+    /// ...), recognizing rate limits from the status and `x-ratelimit-*` or
+    /// `retry-after` headers
+    pub fn from_response(service: &str, response: &reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+
+        let retry_after = header("retry-after").and_then(|value| value.parse::<i64>().ok());
+        let exhausted = header("x-ratelimit-remaining") == Some("0");
+
+        if status == 429 || (status == 403 && (exhausted || retry_after.is_some())) {
+            let reset_at = header("x-ratelimit-reset")
+                .and_then(|value| value.parse().ok())
+                .or_else(|| retry_after.map(|seconds| chrono::Utc::now().timestamp() + seconds));
+            return AppError::RateLimited { reset_at };
+        }
+
+        match status {
+            401 | 403 => AppError::Unauthorized { status },
+            404 => AppError::NotFound {
+                message: format!("{} returned 404 Not Found", service),
+            },
+            _ => AppError::Http {
+                service: service.to_string(),
+                status,
+            },
+        }
+    }
+
+    /// Build an error from a GraphQL `errors` array, keeping the first entry
+    pub fn from_graphql(errors: &serde_json::Value) -> Self {
+        let first = &errors[0];
+        let error_type = first["type"].as_str().map(str::to_string);
+
+        if error_type.as_deref() == Some("RATE_LIMITED") {
+            return AppError::RateLimited { reset_at: None };
+        }
+
+        AppError::GraphQl {
+            error_type,
+            message: first["message"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| errors.to_string()),
+        }
+    }
+
+    pub fn invalid_response() -> Self {
+        AppError::InvalidResponse {
+            message: "Invalid response structure".to_string(),
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        AppError::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn credentials(message: impl Into<String>) -> Self {
+        AppError::Credentials {
+            message: message.into(),
+        }
+    }
+
+    pub fn storage(message: impl Into<String>) -> Self {
+        AppError::Storage {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Offline { message }
+            | AppError::NotFound { message }
+            | AppError::InvalidResponse { message }
+            | AppError::InvalidInput { message }
+            | AppError::LoginFailed { message, .. }
+            | AppError::Credentials { message }
+            | AppError::Storage { message } => f.write_str(message),
+            AppError::Unauthorized { status } => {
+                write!(f, "The token was rejected or lacks access ({})", status)
+            }
+            AppError::RateLimited {
+                reset_at: Some(reset_at),
+            } => {
+                let reset = chrono::DateTime::from_timestamp(*reset_at, 0)
+                    .map(|reset| {
+                        reset
+                            .with_timezone(&chrono::Local)
+                            .format("%H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_else(|| reset_at.to_string());
+                write!(f, "API rate limit exceeded; it resets at {}", reset)
+            }
+            AppError::RateLimited { reset_at: None } => f.write_str("API rate limit exceeded"),
+            AppError::Http { service, status } => write!(f, "{} API error: {}", service, status),
+            AppError::GraphQl { message, .. } => write!(f, "GraphQL error: {}", message),
+            AppError::NotSignedIn => f.write_str("No account is signed in"),
+            AppError::Unsupported { provider } => write!(
+                f,
+                "This view is only available for GitHub accounts, not {}",
+                provider
+            ),
+            AppError::CredentialsLocked => {
+                f.write_str("Credentials are locked; enter the passphrase to unlock them")
+            }
+            AppError::Cancelled => f.write_str("The request was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", self.code())?;
+        map.serialize_entry("message", &self.to_string())?;
+
+        match self {
+            AppError::Unauthorized { status } => map.serialize_entry("status", status)?,
+            AppError::RateLimited { reset_at } => map.serialize_entry("resetAt", reset_at)?,
+            AppError::Http { service, status } => {
+                map.serialize_entry("service", service)?;
+                map.serialize_entry("status", status)?;
+            }
+            AppError::GraphQl { error_type, .. } => map.serialize_entry("errorType", error_type)?,
+            AppError::Unsupported { provider } => map.serialize_entry("provider", provider)?,
+            AppError::LoginFailed { reason, .. } => map.serialize_entry("reason", reason)?,
+            _ => {}
+        }
+
+        map.end()
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            AppError::InvalidResponse {
+                message: format!("JSON parse error: {}", error),
+            }
+        } else {
+            AppError::Offline {
+                message: format!("Network error: {}", error),
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::storage(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        AppError::storage(error.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(error: rusqlite::Error) -> Self {
+        AppError::storage(format!("History database error: {}", error))
+    }
+}
+
+impl From<keyring::Error> for AppError {
+    fn from(error: keyring::Error) -> Self {
+        AppError::credentials(error.to_string())
+    }
+}
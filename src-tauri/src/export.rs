@@ -0,0 +1,350 @@
+use crate::error::AppError;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+    /// iCalendar, one all-day event per active day
+    Ics,
+}
+
+/// One exported day. The breakdown columns are only known for GitHub days
+/// whose breakdown was fetched.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRow {
+    pub date: String,
+    pub contribution_count: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contribution_level: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commits: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_requests: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issues: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviews: Option<i32>,
+}
+
+impl ExportRow {
+    fn has_breakdown(&self) -> bool {
+        self.commits.is_some()
+            || self.pull_requests.is_some()
+            || self.issues.is_some()
+            || self.reviews.is_some()
+    }
+}
+
+/// Serialize `rows` of `account`. `generated_at` stamps iCalendar events.
+pub fn write(
+    rows: &[ExportRow],
+    format: ExportFormat,
+    account: &str,
+    generated_at: DateTime<Utc>,
+) -> Result<String, AppError> {
+    match format {
+        ExportFormat::Csv => Ok(write_csv(rows)),
+        ExportFormat::Json => serde_json::to_string_pretty(rows).map_err(AppError::from),
+        ExportFormat::Ndjson => {
+            let mut out = String::new();
+            for row in rows {
+                out.push_str(&serde_json::to_string(row)?);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+        ExportFormat::Ics => write_ics(rows, account, generated_at),
+    }
+}
+
+/// `date,count,level` plus `commits,pullRequests,issues,reviews` when any
+/// row has a breakdown; unknown values are left empty
+fn write_csv(rows: &[ExportRow]) -> String {
+    let breakdown = rows.iter().any(ExportRow::has_breakdown);
+    let optional = |value: Option<i32>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    let mut out = String::from("date,count,level");
+    if breakdown {
+        out.push_str(",commits,pullRequests,issues,reviews");
+    }
+    out.push('\n');
+
+    for row in rows {
+        out.push_str(&format!(
+            "{},{},{}",
+            row.date,
+            row.contribution_count,
+            row.contribution_level
+                .map(|l| l.to_string())
+                .unwrap_or_default()
+        ));
+        if breakdown {
+            out.push_str(&format!(
+                ",{},{},{},{}",
+                optional(row.commits),
+                optional(row.pull_requests),
+                optional(row.issues),
+                optional(row.reviews)
+            ));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn write_ics(
+    rows: &[ExportRow],
+    account: &str,
+    generated_at: DateTime<Utc>,
+) -> Result<String, AppError> {
+    let stamp = generated_at.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//github-widget//contributions//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for row in rows.iter().filter(|row| row.contribution_count > 0) {
+        let date = NaiveDate::parse_from_str(&row.date, "%Y-%m-%d")
+            .map_err(|e| AppError::invalid_input(format!("Invalid date '{}': {}", row.date, e)))?;
+        let end = date + Days::new(1);
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}-{}@github-widget",
+            date.format("%Y%m%d"),
+            account
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")));
+        lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+        lines.push(format!(
+            "SUMMARY:{} contribution{}",
+            row.contribution_count,
+            if row.contribution_count == 1 { "" } else { "s" }
+        ));
+        if row.has_breakdown() {
+            let description = format!(
+                "Commits: {}, Pull requests: {}, Issues: {}, Reviews: {}",
+                row.commits.unwrap_or(0),
+                row.pull_requests.unwrap_or(0),
+                row.issues.unwrap_or(0),
+                row.reviews.unwrap_or(0)
+            );
+            lines.push(format!("DESCRIPTION:{}", escape_ics(&description)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    // iCalendar lines end in CRLF and are folded at 75 octets
+    Ok(lines.iter().map(|line| fold_ics(line) + "\r\n").collect())
+}
+
+/// Escape iCalendar TEXT values
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line so no physical line exceeds 75 octets, continuing
+/// with a leading space and never splitting a UTF-8 character
+fn fold_ics(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn rows() -> Vec<ExportRow> {
+        vec![
+            ExportRow {
+                date: "2024-02-28".to_string(),
+                contribution_count: 0,
+                contribution_level: Some(0),
+                ..Default::default()
+            },
+            ExportRow {
+                date: "2024-02-29".to_string(),
+                contribution_count: 7,
+                contribution_level: Some(3),
+                commits: Some(4),
+                pull_requests: Some(1),
+                issues: Some(0),
+                reviews: Some(2),
+            },
+            ExportRow {
+                date: "2024-03-01".to_string(),
+                contribution_count: 1,
+                contribution_level: Some(1),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn generated_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 2, 12, 0, 0).unwrap()
+    }
+
+    fn export(format: ExportFormat) -> String {
+        write(&rows(), format, "github:octocat@github.com", generated_at()).unwrap()
+    }
+
+    fn parse_csv(text: &str) -> Vec<ExportRow> {
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+
+        lines
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                assert_eq!(fields.len(), header.len());
+                let column = |name: &str| {
+                    header
+                        .iter()
+                        .position(|h| *h == name)
+                        .map(|i| fields[i])
+                        .filter(|value| !value.is_empty())
+                };
+
+                ExportRow {
+                    date: column("date").unwrap().to_string(),
+                    contribution_count: column("count").unwrap().parse().unwrap(),
+                    contribution_level: column("level").map(|v| v.parse().unwrap()),
+                    commits: column("commits").map(|v| v.parse().unwrap()),
+                    pull_requests: column("pullRequests").map(|v| v.parse().unwrap()),
+                    issues: column("issues").map(|v| v.parse().unwrap()),
+                    reviews: column("reviews").map(|v| v.parse().unwrap()),
+                }
+            })
+            .collect()
+    }
+
+    /// Unfold lines and read back each event's date, count and breakdown
+    fn parse_ics(text: &str) -> Vec<ExportRow> {
+        assert!(text.ends_with("\r\n"));
+        let unfolded = text.replace("\r\n ", "");
+        let mut rows = Vec::new();
+        let mut row = None;
+
+        for line in unfolded.split("\r\n") {
+            match line.split_once(':') {
+                Some(("BEGIN", "VEVENT")) => row = Some(ExportRow::default()),
+                Some(("END", "VEVENT")) => rows.push(row.take().unwrap()),
+                Some(("DTSTART;VALUE=DATE", value)) => {
+                    let date = NaiveDate::parse_from_str(value, "%Y%m%d").unwrap();
+                    row.as_mut().unwrap().date = date.to_string();
+                }
+                Some(("SUMMARY", value)) => {
+                    let count = value.split(' ').next().unwrap();
+                    row.as_mut().unwrap().contribution_count = count.parse().unwrap();
+                }
+                Some(("DESCRIPTION", value)) => {
+                    let value = value.replace("\\,", ",");
+                    let counts: Vec<i32> = value
+                        .split(", ")
+                        .map(|part| part.rsplit(": ").next().unwrap().parse().unwrap())
+                        .collect();
+                    let row = row.as_mut().unwrap();
+                    row.commits = Some(counts[0]);
+                    row.pull_requests = Some(counts[1]);
+                    row.issues = Some(counts[2]);
+                    row.reviews = Some(counts[3]);
+                }
+                _ => {}
+            }
+        }
+
+        rows
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let text = export(ExportFormat::Csv);
+        assert!(text.starts_with("date,count,level,commits,pullRequests,issues,reviews\n"));
+        assert_eq!(parse_csv(&text), rows());
+    }
+
+    #[test]
+    fn csv_omits_breakdown_columns_when_unknown() {
+        let rows: Vec<ExportRow> = rows()
+            .into_iter()
+            .filter(|row| !row.has_breakdown())
+            .collect();
+        let text = write(&rows, ExportFormat::Csv, "account", generated_at()).unwrap();
+
+        assert!(text.starts_with("date,count,level\n"));
+        assert_eq!(parse_csv(&text), rows);
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let text = export(ExportFormat::Json);
+        let parsed: Vec<ExportRow> = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed, rows());
+    }
+
+    #[test]
+    fn ndjson_round_trips() {
+        let text = export(ExportFormat::Ndjson);
+        let parsed: Vec<ExportRow> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(text.lines().count(), rows().len());
+        assert_eq!(parsed, rows());
+    }
+
+    #[test]
+    fn ics_round_trips_active_days() {
+        let text = export(ExportFormat::Ics);
+        let expected: Vec<ExportRow> = rows()
+            .into_iter()
+            .filter(|row| row.contribution_count > 0)
+            .map(|row| ExportRow {
+                contribution_level: None,
+                ..row
+            })
+            .collect();
+
+        assert!(text.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(text.contains("DTEND;VALUE=DATE:20240301\r\n"));
+        assert!(text.contains("DTSTAMP:20240302T120000Z\r\n"));
+        assert_eq!(parse_ics(&text), expected);
+    }
+
+    #[test]
+    fn ics_folds_long_lines() {
+        let account = "a".repeat(120);
+        let text = write(&rows(), ExportFormat::Ics, &account, generated_at()).unwrap();
+
+        assert!(text.split("\r\n").all(|line| line.len() <= 75));
+        assert!(text
+            .replace("\r\n ", "")
+            .contains(&format!("UID:20240229-{}@github-widget", account)));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::commands::ContributionDay;
+use crate::error::AppError;
+use chrono::{Local, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::Manager;
+
+/// Schema version kept in `PRAGMA user_version`
+const SCHEMA_VERSION: i32 = 1;
+
+/// How long a statement waits on a lock held by another connection (a
+/// concurrent fetch or the scheduler) before failing with `SQLITE_BUSY`
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A stored day of an account's calendar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryDay {
+    pub date: String,
+    pub contribution_count: i32,
+    /// When the day was first and most recently seen in a fetch (Unix seconds)
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+/// A past day whose count differed from what an earlier fetch saw
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryChange {
+    pub date: String,
+    pub previous_count: i32,
+    pub contribution_count: i32,
+    pub observed_at: i64,
+}
+
+/// Upsert fetched `days` for `account` (an account id), stamping them as
+/// seen now. Days before today whose count changed are logged as changes;
+/// today's count is still growing, so it is not.
+///
+/// A zero never replaces a stored count: providers fill dates outside what
+/// they still keep (GitLab's public calendar, Gitea's heatmap) with zeros,
+/// and those must not erase history recorded while the data existed.
+pub fn record(
+    account: &str,
+    days: &[ContributionDay],
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    let mut conn = open(app_handle)?;
+    let now = Utc::now().timestamp();
+    let today = Local::now().date_naive().to_string();
+
+    let tx = conn.transaction()?;
+    {
+        let mut previous = tx.prepare_cached(
+            "SELECT contribution_count FROM contribution_days WHERE account = ?1 AND date = ?2",
+        )?;
+        let mut upsert = tx.prepare_cached(
+            "INSERT INTO contribution_days (account, date, contribution_count, first_seen, last_seen)
+             VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT (account, date) DO UPDATE
+             SET contribution_count = excluded.contribution_count, last_seen = excluded.last_seen",
+        )?;
+        let mut change = tx.prepare_cached(
+            "INSERT INTO contribution_changes
+                 (account, date, previous_count, contribution_count, observed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+
+        for day in days {
+            let count: Option<i32> = previous
+                .query_row(params![account, day.date], |row| row.get(0))
+                .map(Some)
+                .or_else(|e| match e {
+                    rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                    e => Err(e),
+                })?;
+
+            if day.contribution_count == 0 && count.is_some_and(|count| count > 0) {
+                continue;
+            }
+
+            if let Some(count) = count.filter(|count| *count != day.contribution_count) {
+                if day.date < today {
+                    change.execute(params![
+                        account,
+                        day.date,
+                        count,
+                        day.contribution_count,
+                        now
+                    ])?;
+                }
+            }
+
+            upsert.execute(params![account, day.date, day.contribution_count, now])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Stored days of `account` within the inclusive range (unbounded on a
+/// missing end), oldest first
+pub fn query_days(
+    account: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<HistoryDay>, AppError> {
+    let conn = open(app_handle)?;
+    let mut statement = conn.prepare(
+        "SELECT date, contribution_count, first_seen, last_seen FROM contribution_days
+         WHERE account = ?1 AND (?2 IS NULL OR date >= ?2) AND (?3 IS NULL OR date <= ?3)
+         ORDER BY date",
+    )?;
+
+    let days = statement
+        .query_map(
+            params![
+                account,
+                from.map(|d| d.to_string()),
+                to.map(|d| d.to_string())
+            ],
+            |row| {
+                Ok(HistoryDay {
+                    date: row.get(0)?,
+                    contribution_count: row.get(1)?,
+                    first_seen: row.get(2)?,
+                    last_seen: row.get(3)?,
+                })
+            },
+        )?
+        .collect::<Result<_, _>>()?;
+
+    Ok(days)
+}
+
+/// Retroactive changes to days of `account` within the inclusive range,
+/// in the order they were observed
+pub fn query_changes(
+    account: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<HistoryChange>, AppError> {
+    let conn = open(app_handle)?;
+    let mut statement = conn.prepare(
+        "SELECT date, previous_count, contribution_count, observed_at FROM contribution_changes
+         WHERE account = ?1 AND (?2 IS NULL OR date >= ?2) AND (?3 IS NULL OR date <= ?3)
+         ORDER BY observed_at, date",
+    )?;
+
+    let changes = statement
+        .query_map(
+            params![
+                account,
+                from.map(|d| d.to_string()),
+                to.map(|d| d.to_string())
+            ],
+            |row| {
+                Ok(HistoryChange {
+                    date: row.get(0)?,
+                    previous_count: row.get(1)?,
+                    contribution_count: row.get(2)?,
+                    observed_at: row.get(3)?,
+                })
+            },
+        )?
+        .collect::<Result<_, _>>()?;
+
+    Ok(changes)
+}
+
+/// Open the database, creating or upgrading its schema as needed.
+///
+/// Every call opens its own connection, so writers wait on each other's
+/// locks, and WAL mode lets readers run alongside a writer.
+fn open(app_handle: &tauri::AppHandle) -> Result<Connection, AppError> {
+    let path = get_history_path(app_handle)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let conn = Connection::open(&path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    if version < 1 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS contribution_days (
+                 account TEXT NOT NULL,
+                 date TEXT NOT NULL,
+                 contribution_count INTEGER NOT NULL,
+                 first_seen INTEGER NOT NULL,
+                 last_seen INTEGER NOT NULL,
+                 PRIMARY KEY (account, date)
+             ) WITHOUT ROWID;
+             CREATE TABLE IF NOT EXISTS contribution_changes (
+                 account TEXT NOT NULL,
+                 date TEXT NOT NULL,
+                 previous_count INTEGER NOT NULL,
+                 contribution_count INTEGER NOT NULL,
+                 observed_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS contribution_changes_by_day
+                 ON contribution_changes (account, date);",
+        )?;
+    }
+
+    if version < SCHEMA_VERSION {
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    }
+
+    Ok(conn)
+}
+
+fn get_history_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::storage(format!("Failed to get app data dir: {}", e)))?;
+
+    Ok(app_data_dir.join("history.sqlite3"))
+}
@@ -0,0 +1,110 @@
+use crate::commands::ContributionDay;
+use serde::{Deserialize, Serialize};
+
+/// How counts map to the five color levels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LevelScale {
+    /// The user's `level_thresholds`
+    Fixed,
+    /// Quartiles of the active days, as GitHub's `contributionLevel`
+    #[default]
+    Quartile,
+    /// Logarithmic steps up to the busiest day
+    Log,
+}
+
+/// Colors GitHub's API reports for levels 0 through 4, used for levels
+/// computed here so every source looks alike
+pub const LEVEL_COLORS: [&str; 5] = ["#ebedf0", "#9be9a8", "#40c463", "#30a14e", "#216e39"];
+
+/// Level of a GitHub `ContributionLevel` enum value
+pub fn parse_github_level(value: &str) -> Option<u8> {
+    match value {
+        "NONE" => Some(0),
+        "FIRST_QUARTILE" => Some(1),
+        "SECOND_QUARTILE" => Some(2),
+        "THIRD_QUARTILE" => Some(3),
+        "FOURTH_QUARTILE" => Some(4),
+        _ => None,
+    }
+}
+
+/// Set every day's level and color with `scale`. Quartile levels GitHub
+/// already reported are kept as they are.
+pub fn apply(days: &mut [ContributionDay], scale: LevelScale, thresholds: &[u32; 4]) {
+    match scale {
+        LevelScale::Quartile => {
+            if days.iter().any(|day| day.contribution_level.is_none()) {
+                assign_quartiles(days);
+            }
+        }
+        LevelScale::Fixed => {
+            for day in days.iter_mut() {
+                let count = day.contribution_count.max(0) as u32;
+                let level = thresholds.iter().filter(|min| count >= **min).count();
+                set_level(day, level as u8);
+            }
+        }
+        LevelScale::Log => {
+            let max = days
+                .iter()
+                .map(|day| day.contribution_count)
+                .max()
+                .unwrap_or(0);
+            for day in days.iter_mut() {
+                let level = log_level(day.contribution_count, max);
+                set_level(day, level);
+            }
+        }
+    }
+}
+
+/// Level each day by the quartiles of the counts on active days: up to the
+/// first quartile is level 1, up to the median 2, up to the third quartile 3,
+/// and anything above 4
+pub fn assign_quartiles(days: &mut [ContributionDay]) {
+    let mut active: Vec<i32> = days
+        .iter()
+        .map(|day| day.contribution_count)
+        .filter(|count| *count > 0)
+        .collect();
+    active.sort_unstable();
+
+    let quartiles = [25, 50, 75].map(|percent| nearest_rank(&active, percent));
+
+    for day in days.iter_mut() {
+        let count = day.contribution_count;
+        let level = if count <= 0 {
+            0
+        } else {
+            1 + quartiles
+                .iter()
+                .filter(|quartile| count > **quartile)
+                .count() as u8
+        };
+        set_level(day, level);
+    }
+}
+
+/// The `percent`th percentile of `sorted` by the nearest-rank method
+fn nearest_rank(sorted: &[i32], percent: usize) -> i32 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn log_level(count: i32, max: i32) -> u8 {
+    if count <= 0 || max <= 0 {
+        return 0;
+    }
+    let ratio = (count as f64).ln_1p() / (max as f64).ln_1p();
+    ((ratio * 4.0).ceil() as u8).clamp(1, 4)
+}
+
+fn set_level(day: &mut ContributionDay, level: u8) {
+    day.contribution_level = Some(level);
+    day.color = Some(LEVEL_COLORS[level as usize].to_string());
+}
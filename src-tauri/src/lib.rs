@@ -1,6 +1,25 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod accounts;
 mod commands;
 mod auth;
+mod device_flow;
+mod error;
+mod export;
+mod history;
+mod levels;
+mod local_git;
+mod providers;
+mod rate_limit;
+pub mod render;
+mod retry;
+mod scheduler;
+mod settings;
+mod stats;
+
+pub use commands::ContributionDay;
+pub use error::AppError;
+pub use settings::WeekStart;
+pub use stats::{compute as compute_stats, ContributionStats};
 
 
 
@@ -9,12 +28,47 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
+        .setup(|app| {
+            scheduler::start(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::fetch_contributions,
+            commands::fetch_contributions_range,
+            commands::fetch_contribution_breakdown,
+            commands::fetch_repository_contributions,
+            commands::fetch_local_contributions,
+            commands::fetch_merged_contributions,
+            commands::fetch_contribution_history,
+            commands::fetch_history_changes,
+            commands::compute_contribution_stats,
+            commands::render_heatmap_svg,
+            commands::export_heatmap_png,
             commands::clear_cache,
+            commands::export_contributions,
+            commands::get_rate_limit_status,
+            commands::get_refresh_interval,
+            commands::set_refresh_interval,
+            commands::get_settings,
+            commands::update_settings,
+            commands::get_retry_policy,
+            commands::set_retry_policy,
+            commands::cancel_pending_fetches,
             commands::save_github_token,
-            commands::get_github_token,
-            commands::delete_github_token
+            commands::get_token_status,
+            commands::delete_github_token,
+            commands::validate_github_token,
+            commands::get_credential_backend,
+            commands::unlock_credentials,
+            commands::migrate_credentials,
+            commands::set_account_host,
+            commands::get_account_config,
+            commands::list_accounts,
+            commands::add_account,
+            commands::switch_account,
+            commands::remove_account,
+            commands::start_device_login,
+            commands::complete_device_login
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,307 @@
+use crate::commands::ContributionDay;
+use crate::error::AppError;
+use crate::providers::fill_days;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use git2::{Oid, Repository, Sort};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How deep below each configured directory to look for repositories
+const MAX_SCAN_DEPTH: usize = 6;
+
+/// Directories that never contain repositories worth scanning
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", "target", "vendor"];
+
+/// Result of `scan_contributions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalContributions {
+    pub days: Vec<ContributionDay>,
+    /// Repositories that could not be read; their commits are not counted
+    pub failures: Vec<RepositoryFailure>,
+}
+
+/// A repository skipped while scanning
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryFailure {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Count commits authored by any of `emails` in every repository found under
+/// `directories`, as one `ContributionDay` per date in `from..=to`.
+///
+/// Commits are dated in the author's own timezone, the same way `git log`
+/// shows them. A commit reachable from several clones is only counted once.
+pub fn scan_contributions(
+    directories: &[PathBuf],
+    emails: &[String],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<LocalContributions, AppError> {
+    let emails: HashSet<String> = emails
+        .iter()
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty())
+        .collect();
+
+    if emails.is_empty() {
+        return Err(AppError::invalid_input(
+            "At least one author email is required",
+        ));
+    }
+
+    let mut counts = BTreeMap::new();
+    let mut seen = HashSet::new();
+    let mut failures = Vec::new();
+
+    for repository in find_repositories(directories) {
+        // A single unreadable repository shouldn't hide the others
+        if let Err(e) = count_commits(&repository, &emails, from, to, &mut seen, &mut counts) {
+            failures.push(RepositoryFailure {
+                path: repository,
+                message: e.message().to_string(),
+            });
+        }
+    }
+
+    Ok(LocalContributions {
+        days: fill_days(&counts, from, to),
+        failures,
+    })
+}
+
+/// Walk the directories and return the working trees of all git repositories
+pub fn find_repositories(directories: &[PathBuf]) -> Vec<PathBuf> {
+    let mut repositories = Vec::new();
+    for directory in directories {
+        collect_repositories(directory, 0, &mut repositories);
+    }
+    repositories.sort();
+    repositories.dedup();
+    repositories
+}
+
+fn collect_repositories(directory: &Path, depth: usize, repositories: &mut Vec<PathBuf>) {
+    // `.git` is a directory in normal clones and a file in worktrees/submodules
+    if directory.join(".git").exists() {
+        repositories.push(directory.to_path_buf());
+        return;
+    }
+
+    if depth >= MAX_SCAN_DEPTH {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        // Skip symlinks so link cycles can't trap the walk
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if !file_type.is_dir() {
+            continue;
+        }
+
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+
+        collect_repositories(&entry.path(), depth + 1, repositories);
+    }
+}
+
+/// Add the repository's matching commits within the range to `counts`
+fn count_commits(
+    path: &Path,
+    emails: &HashSet<String>,
+    from: NaiveDate,
+    to: NaiveDate,
+    seen: &mut HashSet<Oid>,
+    counts: &mut BTreeMap<NaiveDate, i32>,
+) -> Result<(), git2::Error> {
+    let repository = Repository::open(path)?;
+    let mut revwalk = repository.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push_glob("refs/heads/*")?;
+
+    // Walking newest-first by commit time, stop once commits are a day older
+    // than the range so timezone offsets can't cut off the first date
+    let cutoff = from
+        .and_hms_opt(0, 0, 0)
+        .map(|start| start.and_utc().timestamp() - 86_400)
+        .unwrap_or(i64::MIN);
+
+    for oid in revwalk {
+        let oid = oid?;
+        if !seen.insert(oid) {
+            continue;
+        }
+
+        let commit = repository.find_commit(oid)?;
+        if commit.time().seconds() < cutoff {
+            break;
+        }
+
+        let author = commit.author();
+        let matches = author
+            .email()
+            .is_some_and(|email| emails.contains(&email.to_lowercase()));
+        if !matches {
+            continue;
+        }
+
+        let when = author.when();
+        let date = FixedOffset::east_opt(when.offset_minutes() * 60).and_then(|offset| {
+            DateTime::from_timestamp(when.seconds(), 0)
+                .map(|timestamp| timestamp.with_timezone(&offset).date_naive())
+        });
+
+        if let Some(date) = date.filter(|date| *date >= from && *date <= to) {
+            *counts.entry(date).or_insert(0) += 1;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh directory under the temp dir, removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "gitpulse-local-git-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Commit an empty tree on top of `HEAD`, authored by `email` at the UTC
+    /// timestamp `at` in a timezone `offset_minutes` east of UTC
+    fn commit(repository: &Repository, email: &str, at: &str, offset_minutes: i32) {
+        let seconds = DateTime::parse_from_rfc3339(at).unwrap().timestamp();
+        let author = Signature::new("Author", email, &Time::new(seconds, offset_minutes)).unwrap();
+
+        let tree_id = repository.treebuilder(None).unwrap().write().unwrap();
+        let tree = repository.find_tree(tree_id).unwrap();
+        let parent = repository
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+
+        repository
+            .commit(Some("HEAD"), &author, &author, at, &tree, &parents)
+            .unwrap();
+    }
+
+    fn scan(directory: &Path, emails: &[&str]) -> LocalContributions {
+        let emails: Vec<String> = emails.iter().map(|email| email.to_string()).collect();
+        scan_contributions(
+            &[directory.to_path_buf()],
+            &emails,
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn count_on(result: &LocalContributions, date: &str) -> i32 {
+        result
+            .days
+            .iter()
+            .find(|day| day.date == date)
+            .map_or(0, |day| day.contribution_count)
+    }
+
+    #[test]
+    fn counts_commits_by_any_of_the_emails_ignoring_case() {
+        let dir = TempDir::new();
+        let repository = Repository::init(dir.0.join("project")).unwrap();
+        commit(&repository, "Me@Example.com", "2024-03-05T12:00:00Z", 0);
+        commit(&repository, "work@example.com", "2024-03-05T13:00:00Z", 0);
+        commit(
+            &repository,
+            "someone@example.com",
+            "2024-03-05T14:00:00Z",
+            0,
+        );
+
+        let result = scan(&dir.0, &["me@example.com", " WORK@example.com "]);
+
+        assert_eq!(count_on(&result, "2024-03-05"), 2);
+        assert_eq!(result.days.len(), 31);
+        assert!(result.failures.is_empty());
+    }
+
+    #[test]
+    fn counts_a_commit_reachable_from_several_clones_once() {
+        let dir = TempDir::new();
+        let origin = Repository::init(dir.0.join("origin")).unwrap();
+        commit(&origin, "me@example.com", "2024-03-05T12:00:00Z", 0);
+
+        let clone_path = dir.0.join("clone");
+        let clone = Repository::clone(dir.0.join("origin").to_str().unwrap(), &clone_path).unwrap();
+        commit(&clone, "me@example.com", "2024-03-06T12:00:00Z", 0);
+
+        let result = scan(&dir.0, &["me@example.com"]);
+
+        assert_eq!(count_on(&result, "2024-03-05"), 1);
+        assert_eq!(count_on(&result, "2024-03-06"), 1);
+    }
+
+    #[test]
+    fn dates_commits_in_the_authors_timezone() {
+        let dir = TempDir::new();
+        let repository = Repository::init(dir.0.join("project")).unwrap();
+        // Late on the 10th in UTC is already the 11th two hours east
+        commit(&repository, "me@example.com", "2024-03-10T23:30:00Z", 120);
+        // Early on the 20th in UTC is still the 19th five hours west
+        commit(&repository, "me@example.com", "2024-03-20T01:00:00Z", -300);
+
+        let result = scan(&dir.0, &["me@example.com"]);
+
+        assert_eq!(count_on(&result, "2024-03-10"), 0);
+        assert_eq!(count_on(&result, "2024-03-11"), 1);
+        assert_eq!(count_on(&result, "2024-03-19"), 1);
+        assert_eq!(count_on(&result, "2024-03-20"), 0);
+    }
+
+    #[test]
+    fn reports_unreadable_repositories_and_counts_the_rest() {
+        let dir = TempDir::new();
+        let repository = Repository::init(dir.0.join("good")).unwrap();
+        commit(&repository, "me@example.com", "2024-03-05T12:00:00Z", 0);
+        fs::create_dir_all(dir.0.join("broken")).unwrap();
+        fs::write(dir.0.join("broken").join(".git"), "not a repository").unwrap();
+
+        let result = scan(&dir.0, &["me@example.com"]);
+
+        assert_eq!(count_on(&result, "2024-03-05"), 1);
+        assert_eq!(result.failures.len(), 1);
+        assert_eq!(result.failures[0].path, dir.0.join("broken"));
+    }
+}
@@ -0,0 +1,57 @@
+use super::{default_window, fill_days};
+use crate::commands::ContributionDay;
+use crate::error::AppError;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::BTreeMap;
+
+pub const DEFAULT_HOST: &str = "codeberg.org";
+
+/// Fetch Gitea/Forgejo activity for the window (the trailing year by default)
+/// from `/api/v1/users/{username}/heatmap` on the instance at `base_url`.
+///
+/// The heatmap is a list of `{timestamp, contributions}` pairs at sub-day
+/// granularity, so entries are summed per local date. Instances only keep
+/// roughly one year of heatmap data.
+pub async fn fetch_from_gitea(
+    username: &str,
+    base_url: &str,
+    token: Option<&str>,
+    window: Option<(NaiveDate, NaiveDate)>,
+) -> Result<Vec<ContributionDay>, AppError> {
+    let (from, to) = window.unwrap_or_else(default_window);
+
+    let client = crate::retry::client();
+    let response = crate::retry::send(|| {
+        let request = client
+            .get(format!("{}/api/v1/users/{}/heatmap", base_url, username))
+            .header("Accept", "application/json")
+            .header("User-Agent", "github-widget");
+
+        // Add authorization if token provided
+        match token {
+            Some(t) => request.header("Authorization", format!("token {}", t)),
+            None => request,
+        }
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response("Gitea", &response));
+    }
+
+    let heatmap: Vec<serde_json::Value> = response.json().await?;
+
+    let mut counts = BTreeMap::new();
+    for entry in heatmap {
+        let date = entry["timestamp"]
+            .as_i64()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .map(|timestamp| timestamp.with_timezone(&Local).date_naive());
+
+        if let Some(date) = date.filter(|date| *date >= from && *date <= to) {
+            *counts.entry(date).or_insert(0) += entry["contributions"].as_i64().unwrap_or(0) as i32;
+        }
+    }
+
+    Ok(fill_days(&counts, from, to))
+}
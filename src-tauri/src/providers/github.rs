@@ -0,0 +1,748 @@
+use crate::accounts::DEFAULT_HOST;
+use crate::commands::{
+    ContributionBreakdown, ContributionDay, RepositoryContributions, RepositoryList,
+};
+use crate::error::AppError;
+use crate::levels;
+use chrono::{Months, NaiveDate};
+use futures::{StreamExt, TryStreamExt};
+use std::collections::{BTreeMap, HashSet};
+
+/// Most GraphQL requests a single fetch keeps in flight; long ranges split
+/// into dozens of queries, which GitHub's secondary rate limits punish
+const MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// GraphQL endpoint for a host (GitHub Enterprise Server serves it under `/api`)
+pub fn graphql_url(host: &str) -> String {
+    if host == DEFAULT_HOST {
+        "https://api.github.com/graphql".to_string()
+    } else {
+        format!("https://{}/api/graphql", host)
+    }
+}
+
+/// Web root for a host, where OAuth endpoints live
+pub fn web_url(host: &str) -> String {
+    format!("https://{}", host)
+}
+
+/// REST API root for a host (GitHub Enterprise Server serves it under `/api/v3`)
+pub fn rest_url(host: &str) -> String {
+    if host == DEFAULT_HOST {
+        "https://api.github.com".to_string()
+    } else {
+        format!("https://{}/api/v3", host)
+    }
+}
+
+/// Split a date range into consecutive windows of at most one year,
+/// since GitHub rejects `contributionsCollection` spans longer than that
+fn split_into_year_windows(from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    split_into_windows(from, to, 12)
+}
+
+/// Split a date range into consecutive windows spanning at most `months`
+fn split_into_windows(from: NaiveDate, to: NaiveDate, months: u32) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut windows = Vec::new();
+    let mut start = from;
+
+    loop {
+        let end = start
+            .checked_add_months(Months::new(months))
+            .and_then(|d| d.pred_opt())
+            .map_or(to, |d| d.min(to));
+        windows.push((start, end));
+
+        match end.succ_opt() {
+            Some(next) if next <= to => start = next,
+            _ => break,
+        }
+    }
+
+    windows
+}
+
+/// Fetch the yearly windows, a few at a time, and merge them into one
+/// sorted, de-duplicated list of days
+pub async fn fetch_range_from_github(
+    username: &str,
+    host: &str,
+    token: Option<&str>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<ContributionDay>, AppError> {
+    let requests = split_into_year_windows(from, to)
+        .into_iter()
+        .map(|window| fetch_from_github(username, host, token, Some(window)));
+    let windows: Vec<_> = futures::stream::iter(requests)
+        .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+
+    Ok(merge_windows(windows, from, to))
+}
+
+/// Join per-window calendars into one chronological run of days within
+/// `from..=to`. GitHub levels each window by its own quartiles, so days
+/// from several windows are leveled again over the whole range.
+fn merge_windows(
+    windows: Vec<Vec<ContributionDay>>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<ContributionDay> {
+    let window_count = windows.len();
+
+    // Keyed by ISO date, so iteration order is chronological
+    let mut merged = BTreeMap::new();
+    for day in windows.into_iter().flatten() {
+        let in_range = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d")
+            .map(|date| date >= from && date <= to)
+            .unwrap_or(false);
+        if in_range {
+            merged.insert(day.date.clone(), day);
+        }
+    }
+
+    let mut days: Vec<ContributionDay> = merged.into_values().collect();
+    if window_count > 1 {
+        crate::levels::assign_quartiles(&mut days);
+    }
+    days
+}
+
+/// Fetch data from GitHub GraphQL API, optionally limited to a date window
+/// of at most one year
+pub async fn fetch_from_github(
+    username: &str,
+    host: &str,
+    token: Option<&str>,
+    window: Option<(NaiveDate, NaiveDate)>,
+) -> Result<Vec<ContributionDay>, AppError> {
+    let query = r#"
+        query($login: String!, $from: DateTime, $to: DateTime) {
+            user(login: $login) {
+                contributionsCollection(from: $from, to: $to) {
+                    contributionCalendar {
+                        weeks {
+                            contributionDays {
+                                date
+                                contributionCount
+                                contributionLevel
+                                color
+                            }
+                        }
+                    }
+                }
+            }
+            rateLimit {
+                limit
+                cost
+                remaining
+                resetAt
+            }
+        }
+    "#;
+
+    let data = graphql_request(host, token, query, window_variables(username, window)).await?;
+
+    // Extract and flatten contribution days
+    let weeks = data["user"]["contributionsCollection"]["contributionCalendar"]["weeks"]
+        .as_array()
+        .ok_or_else(AppError::invalid_response)?;
+
+    let mut days = Vec::new();
+    for week in weeks {
+        if let Some(contribution_days) = week["contributionDays"].as_array() {
+            for day in contribution_days {
+                days.push(ContributionDay {
+                    date: day["date"].as_str().unwrap_or("").to_string(),
+                    contribution_count: day["contributionCount"].as_i64().unwrap_or(0) as i32,
+                    contribution_level: day["contributionLevel"]
+                        .as_str()
+                        .and_then(levels::parse_github_level),
+                    color: day["color"].as_str().map(str::to_string),
+                });
+            }
+        }
+    }
+
+    Ok(days)
+}
+
+/// Contribution connections on `contributionsCollection` that yield one
+/// node per contribution and are counted individually
+#[derive(Debug, Clone, Copy)]
+enum ContributionKind {
+    PullRequest,
+    Issue,
+    Review,
+}
+
+impl ContributionKind {
+    const ALL: [ContributionKind; 3] = [
+        ContributionKind::PullRequest,
+        ContributionKind::Issue,
+        ContributionKind::Review,
+    ];
+
+    fn connection(self) -> &'static str {
+        match self {
+            ContributionKind::PullRequest => "pullRequestContributions",
+            ContributionKind::Issue => "issueContributions",
+            ContributionKind::Review => "pullRequestReviewContributions",
+        }
+    }
+}
+
+/// Fetch commits, pull requests, issues and reviews for the range and merge
+/// them into one breakdown per day, including days without activity
+pub async fn fetch_breakdown_from_github(
+    username: &str,
+    host: &str,
+    token: Option<&str>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<ContributionBreakdown>, AppError> {
+    // Commit contributions come as one node per repository per day, so
+    // three-month windows always fit in a single page of 100 nodes
+    let commit_requests = split_into_windows(from, to, 3)
+        .into_iter()
+        .map(|window| fetch_commit_contributions(username, host, token, window));
+
+    // Collected up front: a stream over the lazy iterator's closures isn't
+    // `Send`, which the command futures must be
+    let other_requests = split_into_year_windows(from, to)
+        .into_iter()
+        .flat_map(move |window| {
+            ContributionKind::ALL
+                .into_iter()
+                .map(move |kind| async move {
+                    let nodes =
+                        fetch_paginated_contributions(username, host, token, window, kind).await?;
+                    Ok::<_, AppError>((kind, nodes))
+                })
+        })
+        .collect::<Vec<_>>();
+
+    let commit_windows: Vec<_> = futures::stream::iter(commit_requests)
+        .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+    let other_windows: Vec<_> = futures::stream::iter(other_requests)
+        .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+
+    let mut days = BTreeMap::new();
+    let mut date = from;
+    while date <= to {
+        days.insert(
+            date,
+            ContributionBreakdown {
+                date: date.to_string(),
+                ..Default::default()
+            },
+        );
+        match date.succ_opt() {
+            Some(next) => date = next,
+            None => break,
+        }
+    }
+
+    for node in commit_windows.into_iter().flatten() {
+        let Some(date) = parse_occurred_at(&node) else {
+            continue;
+        };
+        if let Some(day) = days.get_mut(&date) {
+            day.commits += node["commitCount"].as_i64().unwrap_or(0) as i32;
+        }
+    }
+
+    for (kind, nodes) in other_windows {
+        for node in nodes {
+            let Some(date) = parse_occurred_at(&node) else {
+                continue;
+            };
+            if let Some(day) = days.get_mut(&date) {
+                match kind {
+                    ContributionKind::PullRequest => day.pull_requests += 1,
+                    ContributionKind::Issue => day.issues += 1,
+                    ContributionKind::Review => day.reviews += 1,
+                }
+            }
+        }
+    }
+
+    Ok(days.into_values().collect())
+}
+
+/// Fetch commit contribution nodes (`occurredAt`, `commitCount`) for every
+/// repository committed to within the window
+async fn fetch_commit_contributions(
+    username: &str,
+    host: &str,
+    token: Option<&str>,
+    window: (NaiveDate, NaiveDate),
+) -> Result<Vec<serde_json::Value>, AppError> {
+    let query = r#"
+        query($login: String!, $from: DateTime, $to: DateTime) {
+            user(login: $login) {
+                contributionsCollection(from: $from, to: $to) {
+                    commitContributionsByRepository(maxRepositories: 100) {
+                        contributions(first: 100) {
+                            nodes {
+                                occurredAt
+                                commitCount
+                            }
+                        }
+                    }
+                }
+            }
+            rateLimit {
+                limit
+                cost
+                remaining
+                resetAt
+            }
+        }
+    "#;
+
+    let mut data =
+        graphql_request(host, token, query, window_variables(username, Some(window))).await?;
+
+    let repositories = data["user"]["contributionsCollection"]["commitContributionsByRepository"]
+        .as_array_mut()
+        .ok_or_else(AppError::invalid_response)?;
+
+    let mut nodes = Vec::new();
+    for repository in repositories {
+        if let Some(contributions) = repository["contributions"]["nodes"].as_array_mut() {
+            nodes.append(contributions);
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// Fetch every node of a paginated contribution connection within the window
+async fn fetch_paginated_contributions(
+    username: &str,
+    host: &str,
+    token: Option<&str>,
+    window: (NaiveDate, NaiveDate),
+    kind: ContributionKind,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    let query = format!(
+        r#"
+        query($login: String!, $from: DateTime, $to: DateTime, $after: String) {{
+            user(login: $login) {{
+                contributionsCollection(from: $from, to: $to) {{
+                    {}(first: 100, after: $after) {{
+                        nodes {{
+                            occurredAt
+                        }}
+                        pageInfo {{
+                            hasNextPage
+                            endCursor
+                        }}
+                    }}
+                }}
+            }}
+            rateLimit {{
+                limit
+                cost
+                remaining
+                resetAt
+            }}
+        }}
+    "#,
+        kind.connection()
+    );
+
+    let mut nodes = Vec::new();
+    let mut after: Option<String> = None;
+
+    loop {
+        let mut variables = window_variables(username, Some(window));
+        if let Some(cursor) = &after {
+            variables["after"] = cursor.clone().into();
+        }
+
+        let mut data = graphql_request(host, token, &query, variables).await?;
+        let connection = &mut data["user"]["contributionsCollection"][kind.connection()];
+
+        let page = connection["nodes"]
+            .as_array_mut()
+            .ok_or_else(AppError::invalid_response)?;
+        nodes.append(page);
+
+        let page_info = &connection["pageInfo"];
+        match page_info["endCursor"].as_str() {
+            Some(cursor) if page_info["hasNextPage"].as_bool() == Some(true) => {
+                after = Some(cursor.to_string());
+            }
+            _ => break,
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// `...ByRepository` connections on `contributionsCollection`, with the node
+/// fields to request and the field counting every repository the connection
+/// could list. Commit nodes carry a `commitCount`, the rest count once.
+const REPOSITORY_CONNECTIONS: [(&str, &str, &str); 4] = [
+    (
+        "commitContributionsByRepository",
+        "occurredAt commitCount",
+        "totalRepositoriesWithContributedCommits",
+    ),
+    (
+        "pullRequestContributionsByRepository",
+        "occurredAt",
+        "totalRepositoriesWithContributedPullRequests",
+    ),
+    (
+        "issueContributionsByRepository",
+        "occurredAt",
+        "totalRepositoriesWithContributedIssues",
+    ),
+    (
+        "pullRequestReviewContributionsByRepository",
+        "occurredAt",
+        "totalRepositoriesWithContributedPullRequestReviews",
+    ),
+];
+
+/// Fetch all `...ByRepository` connections for the range, a few at a time,
+/// and fold them into per-repository daily counts, busiest repository first
+pub async fn fetch_repositories_from_github(
+    username: &str,
+    host: &str,
+    token: Option<&str>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<RepositoryList, AppError> {
+    let requests = split_into_year_windows(from, to)
+        .into_iter()
+        .flat_map(move |window| {
+            REPOSITORY_CONNECTIONS
+                .into_iter()
+                .map(move |(connection, node_fields, total_field)| {
+                    fetch_contributions_by_repository(
+                        username,
+                        host,
+                        token,
+                        window,
+                        (connection, node_fields, total_field),
+                    )
+                })
+        })
+        .collect::<Vec<_>>();
+    let results: Vec<_> = futures::stream::iter(requests)
+        .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+    let truncated = results.iter().any(|(_, truncated)| *truncated);
+
+    let mut repositories: BTreeMap<String, (RepositoryContributions, BTreeMap<NaiveDate, i32>)> =
+        BTreeMap::new();
+
+    for (repository, nodes) in results.into_iter().flat_map(|(entries, _)| entries) {
+        let name_with_owner = repository["nameWithOwner"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        let (_, counts) = repositories
+            .entry(name_with_owner.clone())
+            .or_insert_with(|| {
+                let info = RepositoryContributions {
+                    name_with_owner,
+                    is_private: repository["isPrivate"].as_bool().unwrap_or(false),
+                    primary_language: repository["primaryLanguage"]["name"]
+                        .as_str()
+                        .map(str::to_string),
+                    total_count: 0,
+                    days: Vec::new(),
+                };
+                (info, BTreeMap::new())
+            });
+
+        for node in nodes {
+            let Some(date) = parse_occurred_at(&node) else {
+                continue;
+            };
+            if date >= from && date <= to {
+                *counts.entry(date).or_insert(0) +=
+                    node["commitCount"].as_i64().unwrap_or(1) as i32;
+            }
+        }
+    }
+
+    let mut grouped: Vec<RepositoryContributions> = repositories
+        .into_values()
+        .map(|(mut info, counts)| {
+            info.total_count = counts.values().sum();
+            info.days = counts
+                .into_iter()
+                .map(|(date, contribution_count)| {
+                    ContributionDay::new(date.to_string(), contribution_count)
+                })
+                .collect();
+            info
+        })
+        .filter(|info| info.total_count > 0)
+        .collect();
+
+    grouped.sort_by_key(|info| std::cmp::Reverse(info.total_count));
+    Ok(RepositoryList {
+        repositories: grouped,
+        truncated,
+    })
+}
+
+/// Fetch one `...ByRepository` connection within the window, returning each
+/// repository object together with all of its contribution nodes, and
+/// whether GitHub left repositories out of the connection's 100.
+///
+/// The nested `contributions` connections cannot be addressed one repository
+/// at a time, so later pages re-run the query with a shared `after` cursor
+/// and only keep repositories that still reported a next page. That only
+/// works while every such repository reports the same `endCursor` (GitHub's
+/// cursors here are page offsets); if they ever differ the fetch fails
+/// rather than silently skipping or repeating contributions.
+async fn fetch_contributions_by_repository(
+    username: &str,
+    host: &str,
+    token: Option<&str>,
+    window: (NaiveDate, NaiveDate),
+    (connection, node_fields, total_field): (&str, &str, &str),
+) -> Result<(Vec<(serde_json::Value, Vec<serde_json::Value>)>, bool), AppError> {
+    let query = format!(
+        r#"
+        query($login: String!, $from: DateTime, $to: DateTime, $after: String) {{
+            user(login: $login) {{
+                contributionsCollection(from: $from, to: $to) {{
+                    {}
+                    {}(maxRepositories: 100) {{
+                        repository {{
+                            nameWithOwner
+                            isPrivate
+                            primaryLanguage {{
+                                name
+                            }}
+                        }}
+                        contributions(first: 100, after: $after) {{
+                            nodes {{
+                                {}
+                            }}
+                            pageInfo {{
+                                hasNextPage
+                                endCursor
+                            }}
+                        }}
+                    }}
+                }}
+            }}
+            rateLimit {{
+                limit
+                cost
+                remaining
+                resetAt
+            }}
+        }}
+    "#,
+        total_field, connection, node_fields
+    );
+
+    let mut repositories: BTreeMap<String, (serde_json::Value, Vec<serde_json::Value>)> =
+        BTreeMap::new();
+    let mut pending: Option<HashSet<String>> = None;
+    let mut after: Option<String> = None;
+    let mut truncated = false;
+
+    loop {
+        let mut variables = window_variables(username, Some(window));
+        if let Some(cursor) = &after {
+            variables["after"] = cursor.clone().into();
+        }
+
+        let mut data = graphql_request(host, token, &query, variables).await?;
+        let collection = &mut data["user"]["contributionsCollection"];
+        let total = collection[total_field].as_u64();
+        let entries = collection[connection]
+            .as_array_mut()
+            .ok_or_else(AppError::invalid_response)?;
+        if after.is_none() {
+            truncated = total.is_some_and(|total| total > entries.len() as u64);
+        }
+
+        let mut next_cursor: Option<String> = None;
+        let mut still_pending = HashSet::new();
+
+        for entry in entries {
+            let name = entry["repository"]["nameWithOwner"]
+                .as_str()
+                .unwrap_or("")
+                .to_string();
+            if pending
+                .as_ref()
+                .is_some_and(|pending| !pending.contains(&name))
+            {
+                continue;
+            }
+
+            let page_info = entry["contributions"]["pageInfo"].take();
+            let nodes = entry["contributions"]["nodes"]
+                .as_array_mut()
+                .map(std::mem::take)
+                .unwrap_or_default();
+
+            let (_, collected) = repositories
+                .entry(name.clone())
+                .or_insert_with(|| (entry["repository"].take(), Vec::new()));
+            collected.extend(nodes);
+
+            if page_info["hasNextPage"].as_bool() == Some(true) {
+                if let Some(cursor) = page_info["endCursor"].as_str() {
+                    if next_cursor.as_deref().is_some_and(|next| next != cursor) {
+                        return Err(AppError::InvalidResponse {
+                            message: format!(
+                                "Repositories in {} reported different page cursors",
+                                connection
+                            ),
+                        });
+                    }
+                    next_cursor = Some(cursor.to_string());
+                    still_pending.insert(name);
+                }
+            }
+        }
+
+        match next_cursor {
+            Some(cursor) => {
+                after = Some(cursor);
+                pending = Some(still_pending);
+            }
+            None => break,
+        }
+    }
+
+    Ok((repositories.into_values().collect(), truncated))
+}
+
+/// Read the calendar date of a contribution node's `occurredAt` timestamp
+fn parse_occurred_at(node: &serde_json::Value) -> Option<NaiveDate> {
+    node["occurredAt"]
+        .as_str()
+        .and_then(|timestamp| timestamp.get(..10))
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+/// Build the `login`/`from`/`to` variables shared by the contribution queries
+fn window_variables(username: &str, window: Option<(NaiveDate, NaiveDate)>) -> serde_json::Value {
+    let mut variables = serde_json::json!({
+        "login": username
+    });
+
+    // Omitted variables fall back to GitHub's default trailing-year calendar
+    if let Some((from, to)) = window {
+        variables["from"] = format!("{}T00:00:00Z", from).into();
+        variables["to"] = format!("{}T23:59:59Z", to).into();
+    }
+
+    variables
+}
+
+/// POST a query to the host's GraphQL API and return its `data` object
+async fn graphql_request(
+    host: &str,
+    token: Option<&str>,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<serde_json::Value, AppError> {
+    // Don't spend a request while the budget is known to be exhausted
+    crate::rate_limit::check(host, crate::rate_limit::GRAPHQL, token)?;
+
+    let client = crate::retry::client();
+    let body = serde_json::json!({
+        "query": query,
+        "variables": variables
+    });
+
+    let response = crate::retry::send(|| {
+        let request = client
+            .post(graphql_url(host))
+            .header("Content-Type", "application/json")
+            .header("User-Agent", "github-widget")
+            .json(&body);
+
+        // Add authorization if token provided
+        match token {
+            Some(t) => request.header("Authorization", format!("Bearer {}", t)),
+            None => request,
+        }
+    })
+    .await?;
+    crate::rate_limit::record_headers(
+        host,
+        crate::rate_limit::GRAPHQL,
+        token,
+        response.status().as_u16(),
+        response.headers(),
+    );
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response("GitHub", &response));
+    }
+
+    let mut result: serde_json::Value = response.json().await?;
+
+    crate::rate_limit::record_graphql(host, token, &result["data"]["rateLimit"]);
+
+    // Check for GraphQL errors
+    if let Some(errors) = result.get("errors") {
+        return Err(AppError::from_graphql(errors));
+    }
+
+    Ok(result["data"].take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leveled(date: &str, count: i32, level: u8) -> ContributionDay {
+        ContributionDay {
+            contribution_level: Some(level),
+            ..ContributionDay::new(date.to_string(), count)
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn merging_windows_relevels_over_the_whole_range() {
+        // A quiet year where 2 was the top level, then a busy one where 20
+        // was the lowest; across both they land on levels 2 and 3
+        let quiet = vec![leveled("2022-06-01", 1, 1), leveled("2022-06-02", 2, 4)];
+        let busy = vec![leveled("2023-06-01", 20, 1), leveled("2023-06-02", 40, 4)];
+
+        let days = merge_windows(vec![quiet, busy], date("2022-01-01"), date("2023-12-31"));
+
+        let levels: Vec<Option<u8>> = days.iter().map(|day| day.contribution_level).collect();
+        assert_eq!(levels, vec![Some(1), Some(2), Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn a_single_window_keeps_githubs_levels() {
+        let window = vec![leveled("2023-06-01", 1, 2), leveled("2023-06-02", 2, 4)];
+
+        let days = merge_windows(vec![window], date("2023-01-01"), date("2023-12-31"));
+
+        let levels: Vec<Option<u8>> = days.iter().map(|day| day.contribution_level).collect();
+        assert_eq!(levels, vec![Some(2), Some(4)]);
+    }
+}
@@ -0,0 +1,138 @@
+use super::{default_window, fill_days};
+use crate::commands::ContributionDay;
+use crate::error::AppError;
+use chrono::{DateTime, Days, Local, NaiveDate};
+use std::collections::BTreeMap;
+
+pub const DEFAULT_HOST: &str = "gitlab.com";
+
+/// Days back from today that the public profile calendar covers
+const CALENDAR_DAYS: u64 = 365;
+
+/// Fetch GitLab activity for the window (the trailing year by default) from
+/// the instance at `base_url` (such as `https://gitlab.com`).
+///
+/// With a token the paginated user events API is used, which also covers
+/// private projects the token can see and reaches back beyond one year.
+/// Without one, the public profile calendar is used, which only spans the
+/// last year; an explicit window reaching further back is rejected rather
+/// than answered with zeros.
+pub async fn fetch_from_gitlab(
+    username: &str,
+    base_url: &str,
+    token: Option<&str>,
+    window: Option<(NaiveDate, NaiveDate)>,
+) -> Result<Vec<ContributionDay>, AppError> {
+    let (from, to) = window.unwrap_or_else(default_window);
+
+    if token.is_none() && window.is_some() {
+        let today = Local::now().date_naive();
+        let covered_from = today
+            .checked_sub_days(Days::new(CALENDAR_DAYS))
+            .unwrap_or(today);
+        if from < covered_from {
+            return Err(AppError::invalid_input(format!(
+                "GitLab's public calendar only goes back to {}; add a token to fetch earlier dates",
+                covered_from
+            )));
+        }
+    }
+
+    let counts = match token {
+        Some(token) => fetch_event_counts(username, base_url, token, from, to).await?,
+        None => fetch_calendar_counts(username, base_url).await?,
+    };
+
+    Ok(fill_days(&counts, from, to))
+}
+
+/// Read `/users/{username}/calendar.json`, a map of date to event count
+async fn fetch_calendar_counts(
+    username: &str,
+    base_url: &str,
+) -> Result<BTreeMap<NaiveDate, i32>, AppError> {
+    let client = crate::retry::client();
+    let response = crate::retry::send(|| {
+        client
+            .get(format!("{}/users/{}/calendar.json", base_url, username))
+            .header("Accept", "application/json")
+            .header("User-Agent", "github-widget")
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response("GitLab", &response));
+    }
+
+    let calendar: BTreeMap<String, i64> = response.json().await?;
+
+    Ok(calendar
+        .into_iter()
+        .filter_map(|(date, count)| {
+            NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .ok()
+                .map(|date| (date, count as i32))
+        })
+        .collect())
+}
+
+/// Count `/api/v4/users/{username}/events` per local date, following the
+/// `X-Next-Page` pagination header
+async fn fetch_event_counts(
+    username: &str,
+    base_url: &str,
+    token: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<BTreeMap<NaiveDate, i32>, AppError> {
+    // `after` and `before` are exclusive UTC dates; pad both ends so events
+    // near midnight still land in the right local date
+    let after = from.pred_opt().and_then(|d| d.pred_opt()).unwrap_or(from);
+    let before = to.succ_opt().and_then(|d| d.succ_opt()).unwrap_or(to);
+
+    let client = crate::retry::client();
+    let mut counts = BTreeMap::new();
+    let mut page = Some("1".to_string());
+
+    while let Some(current) = page {
+        let response = crate::retry::send(|| {
+            client
+                .get(format!("{}/api/v4/users/{}/events", base_url, username))
+                .query(&[
+                    ("after", after.to_string()),
+                    ("before", before.to_string()),
+                    ("per_page", "100".to_string()),
+                    ("page", current.clone()),
+                ])
+                .header("Authorization", format!("Bearer {}", token))
+                .header("User-Agent", "github-widget")
+        })
+        .await?;
+
+        if !response.status().is_success() {
+            return Err(AppError::from_response("GitLab", &response));
+        }
+
+        page = response
+            .headers()
+            .get("x-next-page")
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string);
+
+        let events: Vec<serde_json::Value> = response.json().await?;
+
+        for event in events {
+            let date = event["created_at"]
+                .as_str()
+                .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+                .map(|timestamp| timestamp.with_timezone(&Local).date_naive());
+
+            if let Some(date) = date.filter(|date| *date >= from && *date <= to) {
+                *counts.entry(date).or_insert(0) += 1;
+            }
+        }
+    }
+
+    Ok(counts)
+}
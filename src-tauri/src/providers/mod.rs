@@ -0,0 +1,94 @@
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+
+use crate::accounts::AccountConfig;
+use crate::commands::ContributionDay;
+use crate::error::AppError;
+use chrono::{Datelike, Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Services that contribution calendars can be fetched from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    GitHub,
+    GitLab,
+    /// Gitea and its forks (Forgejo, Codeberg), which share the same API
+    Gitea,
+}
+
+impl ProviderKind {
+    /// Stable lowercase name, as used in serialized settings
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProviderKind::GitHub => "github",
+            ProviderKind::GitLab => "gitlab",
+            ProviderKind::Gitea => "gitea",
+        }
+    }
+
+    /// Public instance used when an account has no host of its own
+    pub fn default_host(self) -> &'static str {
+        match self {
+            ProviderKind::GitHub => crate::accounts::DEFAULT_HOST,
+            ProviderKind::GitLab => gitlab::DEFAULT_HOST,
+            ProviderKind::Gitea => gitea::DEFAULT_HOST,
+        }
+    }
+}
+
+/// Fetch contribution days from the account's provider. A `window` of `None`
+/// means the trailing-year calendar the provider shows on profiles.
+pub async fn fetch_contributions(
+    source: &AccountConfig,
+    username: &str,
+    token: Option<&str>,
+    window: Option<(NaiveDate, NaiveDate)>,
+) -> Result<Vec<ContributionDay>, AppError> {
+    let host = source.host.as_str();
+    match (source.provider, window) {
+        (ProviderKind::GitHub, Some((from, to))) => {
+            github::fetch_range_from_github(username, host, token, from, to).await
+        }
+        (ProviderKind::GitHub, None) => {
+            github::fetch_from_github(username, host, token, None).await
+        }
+        (ProviderKind::GitLab, window) => {
+            gitlab::fetch_from_gitlab(username, &source.base_url(), token, window).await
+        }
+        (ProviderKind::Gitea, window) => {
+            gitea::fetch_from_gitea(username, &source.base_url(), token, window).await
+        }
+    }
+}
+
+/// The window GitHub's profile calendar covers: today and the 52 full weeks
+/// before it, starting on a Sunday
+pub fn default_window() -> (NaiveDate, NaiveDate) {
+    let today = Local::now().date_naive();
+    let days_back = 364 + u64::from(today.weekday().num_days_from_sunday());
+    let from = today
+        .checked_sub_days(Days::new(days_back))
+        .unwrap_or(today);
+    (from, today)
+}
+
+/// Expand sparse per-date counts into one `ContributionDay` for every date in
+/// `from..=to`, as the heatmap expects a gap-free sequence, leveled by
+/// quartile like GitHub's calendar
+pub fn fill_days(
+    counts: &BTreeMap<NaiveDate, i32>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<ContributionDay> {
+    let mut days: Vec<ContributionDay> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| ContributionDay::new(date.to_string(), counts.get(&date).copied().unwrap_or(0)))
+        .collect();
+    crate::levels::assign_quartiles(&mut days);
+    days
+}
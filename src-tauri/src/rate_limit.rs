@@ -0,0 +1,217 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// GitHub's names (`x-ratelimit-resource`) for the budgets of its REST and
+/// GraphQL APIs, which are metered separately
+pub const CORE: &str = "core";
+pub const GRAPHQL: &str = "graphql";
+
+/// API budget last reported for one token on one host and resource
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitStatus {
+    pub host: String,
+    /// Which budget this is, such as `core` or `graphql`
+    pub resource: String,
+    /// Whether the budget belongs to a token rather than the anonymous limit
+    pub authenticated: bool,
+    pub limit: Option<i64>,
+    pub remaining: Option<i64>,
+    /// When the budget refills (Unix seconds)
+    pub reset_at: Option<i64>,
+    /// Set by a secondary rate limit's `Retry-After` (Unix seconds)
+    pub blocked_until: Option<i64>,
+    /// GraphQL points charged for the last query
+    pub last_cost: Option<i64>,
+    pub updated_at: i64,
+}
+
+impl RateLimitStatus {
+    /// When requests may resume, if the budget is currently spent
+    pub fn exhausted_until(&self, now: i64) -> Option<i64> {
+        let reset = self
+            .reset_at
+            .filter(|reset_at| self.remaining == Some(0) && *reset_at > now);
+        let blocked = self.blocked_until.filter(|until| *until > now);
+        reset.max(blocked)
+    }
+}
+
+/// Budgets keyed by host, resource and a hash of the token, so tokens never
+/// sit in memory twice and can't leak through `snapshot`
+static BUDGETS: Mutex<BTreeMap<String, RateLimitStatus>> = Mutex::new(BTreeMap::new());
+
+/// Fail fast with `RateLimited` while the `resource` budget for `token` on
+/// `host` is spent, instead of making a request that would be rejected
+pub fn check(host: &str, resource: &str, token: Option<&str>) -> Result<(), AppError> {
+    let now = Utc::now().timestamp();
+    let Ok(budgets) = BUDGETS.lock() else {
+        return Ok(());
+    };
+
+    match budgets
+        .get(&budget_key(host, resource, token))
+        .and_then(|status| status.exhausted_until(now))
+    {
+        Some(reset_at) => Err(AppError::RateLimited {
+            reset_at: Some(reset_at),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Record the `x-ratelimit-*` headers of a response, and a secondary rate
+/// limit's `retry-after` when the request was rejected. The budget is the
+/// one named by `x-ratelimit-resource`, or `resource` when it is missing.
+pub fn record_headers(
+    host: &str,
+    resource: &str,
+    token: Option<&str>,
+    status: u16,
+    headers: &HeaderMap,
+) {
+    let text = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    let header = |name: &str| text(name).and_then(|value| value.parse::<i64>().ok());
+
+    let limit = header("x-ratelimit-limit");
+    let remaining = header("x-ratelimit-remaining");
+    let reset_at = header("x-ratelimit-reset");
+    let retry_after = header("retry-after").filter(|_| status == 403 || status == 429);
+
+    if limit.is_none() && remaining.is_none() && retry_after.is_none() {
+        return;
+    }
+
+    let resource = text("x-ratelimit-resource").unwrap_or(resource);
+    update(host, resource, token, |budget, now| {
+        budget.limit = limit.or(budget.limit);
+        budget.remaining = remaining.or(budget.remaining);
+        budget.reset_at = reset_at.or(budget.reset_at);
+        if let Some(seconds) = retry_after {
+            budget.blocked_until = Some(now + seconds);
+        }
+    });
+}
+
+/// Record the `rateLimit { limit cost remaining resetAt }` object that
+/// GraphQL queries ask for
+pub fn record_graphql(host: &str, token: Option<&str>, rate_limit: &serde_json::Value) {
+    if !rate_limit.is_object() {
+        return;
+    }
+
+    let reset_at = rate_limit["resetAt"]
+        .as_str()
+        .and_then(|reset_at| DateTime::parse_from_rfc3339(reset_at).ok())
+        .map(|reset_at| reset_at.timestamp());
+
+    update(host, GRAPHQL, token, |budget, _| {
+        budget.limit = rate_limit["limit"].as_i64().or(budget.limit);
+        budget.remaining = rate_limit["remaining"].as_i64().or(budget.remaining);
+        budget.reset_at = reset_at.or(budget.reset_at);
+        budget.last_cost = rate_limit["cost"].as_i64();
+    });
+}
+
+/// Every budget seen so far, for the status indicator
+pub fn snapshot() -> Vec<RateLimitStatus> {
+    BUDGETS
+        .lock()
+        .map(|budgets| budgets.values().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn update(
+    host: &str,
+    resource: &str,
+    token: Option<&str>,
+    change: impl FnOnce(&mut RateLimitStatus, i64),
+) {
+    let now = Utc::now().timestamp();
+    let Ok(mut budgets) = BUDGETS.lock() else {
+        return;
+    };
+
+    let budget = budgets
+        .entry(budget_key(host, resource, token))
+        .or_insert_with(|| RateLimitStatus {
+            host: host.to_string(),
+            resource: resource.to_string(),
+            authenticated: token.is_some(),
+            ..Default::default()
+        });
+
+    // A budget past its reset time has refilled
+    if budget.reset_at.is_some_and(|reset_at| reset_at <= now) {
+        budget.remaining = budget.limit;
+    }
+
+    change(budget, now);
+    budget.updated_at = now;
+}
+
+fn budget_key(host: &str, resource: &str, token: Option<&str>) -> String {
+    match token {
+        Some(token) => {
+            let mut hasher = DefaultHasher::new();
+            token.hash(&mut hasher);
+            format!("{}#{}#{:016x}", host, resource, hasher.finish())
+        }
+        None => format!("{}#{}#anonymous", host, resource),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(resource: Option<&'static str>, remaining: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("5000"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static(remaining));
+        let reset = (Utc::now().timestamp() + 600).to_string();
+        headers.insert("x-ratelimit-reset", reset.parse().unwrap());
+        if let Some(resource) = resource {
+            headers.insert("x-ratelimit-resource", HeaderValue::from_static(resource));
+        }
+        headers
+    }
+
+    #[test]
+    fn a_spent_rest_budget_does_not_block_graphql() {
+        let host = "core-spent.example.com";
+        record_headers(host, CORE, Some("token"), 200, &headers(None, "0"));
+
+        assert!(check(host, CORE, Some("token")).is_err());
+        assert!(check(host, GRAPHQL, Some("token")).is_ok());
+        assert!(check(host, CORE, Some("other")).is_ok());
+    }
+
+    #[test]
+    fn the_resource_header_wins_over_the_fallback() {
+        let host = "graphql-spent.example.com";
+        record_headers(host, CORE, None, 200, &headers(Some("graphql"), "0"));
+
+        assert!(check(host, GRAPHQL, None).is_err());
+        assert!(check(host, CORE, None).is_ok());
+
+        let budgets: Vec<_> = snapshot()
+            .into_iter()
+            .filter(|status| status.host == host)
+            .collect();
+        assert_eq!(budgets.len(), 1);
+        assert_eq!(budgets[0].resource, GRAPHQL);
+    }
+}
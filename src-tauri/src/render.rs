@@ -0,0 +1,348 @@
+use crate::commands::ContributionDay;
+use crate::error::AppError;
+use crate::levels;
+use crate::settings::WeekStart;
+use crate::stats::{week_of, ContributionStats};
+use chrono::{Datelike, NaiveDate};
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Cell size and spacing, matching the widget's grid
+const CELL: u32 = 11;
+const STEP: u32 = 14;
+
+/// Space for day labels on the left and the header and month labels on top
+const LEFT: u32 = 32;
+const TOP: u32 = 40;
+const PADDING: u32 = 12;
+
+/// Tuffy (public domain, see `fonts/Tuffy-LICENSE.txt`), so text renders
+/// on machines without the fonts the SVG names
+pub const BUNDLED_FONT: &[u8] = include_bytes!("../fonts/Tuffy.ttf");
+
+const FONT: &str = "-apple-system, BlinkMacSystemFont, 'Segoe UI', Helvetica, Arial, sans-serif";
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderTheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+struct Palette {
+    background: &'static str,
+    text: &'static str,
+    muted: &'static str,
+    levels: [&'static str; 5],
+}
+
+impl RenderTheme {
+    fn palette(self) -> Palette {
+        match self {
+            RenderTheme::Light => Palette {
+                background: "#ffffff",
+                text: "#1f2328",
+                muted: "#656d76",
+                levels: levels::LEVEL_COLORS,
+            },
+            RenderTheme::Dark => Palette {
+                background: "#0d1117",
+                text: "#c9d1d9",
+                muted: "#8b949e",
+                levels: ["#161b22", "#0e4429", "#006d32", "#26a641", "#39d353"],
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub theme: RenderTheme,
+    pub week_start: WeekStart,
+    /// Header text; defaults to the total
+    pub title: Option<String>,
+}
+
+/// Raster output settings for `render_png`
+#[derive(Debug, Clone)]
+pub struct PngOptions {
+    /// Pixels per SVG unit, from 0.25 to 8
+    pub scale: f32,
+    /// Whether to draw text with the installed fonts
+    pub system_fonts: bool,
+    /// Font files (TTF or OTF data) to draw text with; the first one also
+    /// stands in for `sans-serif`. Defaults to `BUNDLED_FONT` alone, which
+    /// keeps output identical across machines.
+    pub fonts: Vec<Vec<u8>>,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions {
+            scale: 1.0,
+            system_fonts: false,
+            fonts: vec![BUNDLED_FONT.to_vec()],
+        }
+    }
+}
+
+/// Render `days` to a PNG by rasterizing `render_svg`'s output, without a
+/// browser or webview
+pub fn render_png(
+    days: &[ContributionDay],
+    options: &RenderOptions,
+    stats: Option<&ContributionStats>,
+    png: &PngOptions,
+) -> Result<Vec<u8>, AppError> {
+    if !(0.25..=8.0).contains(&png.scale) {
+        return Err(AppError::invalid_input(format!(
+            "Scale must be between 0.25 and 8, not {}",
+            png.scale
+        )));
+    }
+
+    let svg = render_svg(days, options, stats);
+
+    let mut usvg_options = usvg::Options::default();
+    let fontdb = usvg_options.fontdb_mut();
+    for font in &png.fonts {
+        fontdb.load_font_data(font.clone());
+    }
+    let bundled_family = fontdb
+        .faces()
+        .next()
+        .and_then(|face| face.families.first())
+        .map(|(family, _)| family.clone());
+    if let Some(family) = bundled_family {
+        fontdb.set_sans_serif_family(family);
+    }
+    if png.system_fonts {
+        fontdb.load_system_fonts();
+    }
+    let tree = usvg::Tree::from_str(&svg, &usvg_options)
+        .map_err(|e| AppError::invalid_input(format!("Invalid SVG: {}", e)))?;
+
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(png.scale)
+        .ok_or_else(|| AppError::invalid_input("Image size is out of range"))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| AppError::invalid_input("Image size is out of range"))?;
+
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(png.scale, png.scale),
+        &mut pixmap.as_mut(),
+    );
+
+    pixmap
+        .encode_png()
+        .map_err(|e| AppError::storage(format!("PNG encoding failed: {}", e)))
+}
+
+/// Render `days` as a standalone SVG calendar: one column per week, with
+/// month and weekday labels, a legend, and a stats line when `stats` is
+/// given. Days without a level are leveled by quartile.
+pub fn render_svg(
+    days: &[ContributionDay],
+    options: &RenderOptions,
+    stats: Option<&ContributionStats>,
+) -> String {
+    let palette = options.theme.palette();
+
+    let mut days: Vec<(NaiveDate, &ContributionDay)> = days
+        .iter()
+        .filter_map(|day| {
+            let date = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").ok()?;
+            Some((date, day))
+        })
+        .collect();
+    days.sort_by_key(|(date, _)| *date);
+
+    let mut leveled: Vec<ContributionDay> = days.iter().map(|(_, day)| (*day).clone()).collect();
+    if leveled.iter().any(|day| day.contribution_level.is_none()) {
+        levels::assign_quartiles(&mut leveled);
+    }
+
+    let first_week = days
+        .first()
+        .map(|(date, _)| week_of(*date, options.week_start));
+    let columns = match (first_week, days.last()) {
+        (Some(first), Some((last, _))) => {
+            ((week_of(*last, options.week_start) - first).num_days() / 7 + 1) as u32
+        }
+        _ => 0,
+    };
+
+    // Wide enough for the legend even with only a few weeks
+    let grid_width = columns.max(12) * STEP;
+    let width = LEFT + grid_width + PADDING;
+    let legend_y = TOP + 7 * STEP + 8;
+    let height = legend_y + CELL + if stats.is_some() { 26 } else { 0 } + PADDING;
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="{font}" font-size="10">"#,
+        width = width,
+        height = height,
+        font = FONT,
+    );
+    let _ = write!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width, height, palette.background
+    );
+
+    let total: i64 = days
+        .iter()
+        .map(|(_, day)| i64::from(day.contribution_count))
+        .sum();
+    let title = options.title.clone().unwrap_or_else(|| {
+        format!(
+            "{} contribution{}",
+            total,
+            if total == 1 { "" } else { "s" }
+        )
+    });
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" fill="{}" font-size="12">{}</text>"#,
+        LEFT,
+        PADDING + 8,
+        palette.text,
+        escape(&title)
+    );
+
+    // Month labels over the first week of each month, skipped when too
+    // close to the previous one
+    let mut last_label: Option<u32> = None;
+    let mut last_month = None;
+    for (date, _) in &days {
+        let month = (date.year(), date.month());
+        if last_month == Some(month) {
+            continue;
+        }
+        last_month = Some(month);
+
+        let column = column_of(*date, first_week, options.week_start);
+        if last_label.is_some_and(|previous| column < previous + 3) {
+            continue;
+        }
+        last_label = Some(column);
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+            LEFT + column * STEP,
+            TOP - 6,
+            palette.muted,
+            MONTHS[date.month0() as usize]
+        );
+    }
+
+    let labeled_rows: [(u32, &str); 3] = match options.week_start {
+        WeekStart::Sunday => [(1, "Mon"), (3, "Wed"), (5, "Fri")],
+        WeekStart::Monday => [(0, "Mon"), (2, "Wed"), (4, "Fri")],
+    };
+    for (row, label) in labeled_rows {
+        let _ = write!(
+            svg,
+            r#"<text x="0" y="{}" fill="{}">{}</text>"#,
+            TOP + row * STEP + CELL - 2,
+            palette.muted,
+            label
+        );
+    }
+
+    for ((date, _), day) in days.iter().zip(&leveled) {
+        let column = column_of(*date, first_week, options.week_start);
+        let row = (*date - week_of(*date, options.week_start)).num_days() as u32;
+        let level = day.contribution_level.unwrap_or(0).min(4) as usize;
+        let _ = write!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{cell}" height="{cell}" rx="2" fill="{}"><title>{} contribution{} on {}</title></rect>"#,
+            LEFT + column * STEP,
+            TOP + row * STEP,
+            palette.levels[level],
+            day.contribution_count,
+            if day.contribution_count == 1 { "" } else { "s" },
+            day.date,
+            cell = CELL,
+        );
+    }
+
+    // Legend, right-aligned under the grid
+    let legend_x = LEFT + grid_width - 5 * STEP - 28;
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" fill="{}" text-anchor="end">Less</text>"#,
+        legend_x - 4,
+        legend_y + CELL - 2,
+        palette.muted
+    );
+    for (index, color) in palette.levels.iter().enumerate() {
+        let _ = write!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{cell}" height="{cell}" rx="2" fill="{}"/>"#,
+            legend_x + index as u32 * STEP,
+            legend_y,
+            color,
+            cell = CELL,
+        );
+    }
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" fill="{}">More</text>"#,
+        legend_x + 5 * STEP + 2,
+        legend_y + CELL - 2,
+        palette.muted
+    );
+
+    if let Some(stats) = stats {
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+            LEFT,
+            legend_y + CELL + 20,
+            palette.text,
+            escape(&stats_line(stats))
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn column_of(date: NaiveDate, first_week: Option<NaiveDate>, week_start: WeekStart) -> u32 {
+    let week = week_of(date, week_start);
+    first_week.map_or(0, |first| ((week - first).num_days() / 7) as u32)
+}
+
+fn stats_line(stats: &ContributionStats) -> String {
+    let mut parts = vec![
+        format!("{} active days", stats.active_days),
+        format!("current streak {} days", stats.current_streak.length),
+        format!("longest streak {} days", stats.longest_streak.length),
+    ];
+    if let Some(day) = &stats.busiest_day {
+        parts.push(format!(
+            "busiest day {} ({})",
+            day.start, day.contribution_count
+        ));
+    }
+    parts.join(" · ")
+}
+
+/// Escape text for use in SVG content and attributes
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,238 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// How transient failures (connect errors, timeouts, 5xx and 429 responses)
+/// are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Attempts per request, including the first; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub base_delay_ms: u64,
+    /// Cap on any single delay, including a server's `Retry-After`
+    pub max_delay_ms: u64,
+}
+
+const DEFAULT_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    base_delay_ms: 500,
+    max_delay_ms: 8_000,
+};
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        DEFAULT_POLICY
+    }
+}
+
+/// Requests sent and how many of them were retries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AttemptCount {
+    pub attempts: u32,
+    pub retries: u32,
+}
+
+/// Limits on establishing a connection and on a whole request, so a
+/// stalled host fails (and is retried) instead of hanging the fetch
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+static POLICY: Mutex<RetryPolicy> = Mutex::new(DEFAULT_POLICY);
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// Wakes every request waiting out a backoff so it gives up
+static CANCEL: Notify = Notify::const_new();
+
+tokio::task_local! {
+    static ATTEMPTS: Cell<AttemptCount>;
+}
+
+pub fn policy() -> RetryPolicy {
+    POLICY.lock().map(|policy| *policy).unwrap_or_default()
+}
+
+pub fn set_policy(policy: RetryPolicy) {
+    if let Ok(mut current) = POLICY.lock() {
+        *current = RetryPolicy {
+            max_attempts: policy.max_attempts.max(1),
+            ..policy
+        };
+    }
+}
+
+/// The HTTP client shared by every request, with connect and request
+/// timeouts. Clones share one connection pool.
+pub fn client() -> reqwest::Client {
+    CLIENT
+        .get_or_init(|| {
+            reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default()
+        })
+        .clone()
+}
+
+/// Abort every request currently in flight or waiting to retry
+pub fn cancel_pending() {
+    CANCEL.notify_waiters();
+}
+
+/// Run `fetch`, counting the attempts `send` makes inside it (including
+/// concurrent requests joined within it). Counts also add up into any
+/// enclosing `track_attempts`.
+pub async fn track_attempts<T>(fetch: impl Future<Output = T>) -> (T, AttemptCount) {
+    let (output, count) = ATTEMPTS
+        .scope(Cell::new(AttemptCount::default()), async move {
+            let output = fetch.await;
+            (output, ATTEMPTS.with(Cell::get))
+        })
+        .await;
+
+    let _ = ATTEMPTS.try_with(|outer| {
+        let mut total = outer.get();
+        total.attempts += count.attempts;
+        total.retries += count.retries;
+        outer.set(total);
+    });
+
+    (output, count)
+}
+
+/// Send the request built by `build`, retrying transient failures with
+/// capped exponential backoff and jitter. Once attempts run out the last
+/// response is returned as is, so callers classify it as usual. Both the
+/// request and the wait before a retry end early on `cancel_pending`.
+pub async fn send(
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, AppError> {
+    let policy = policy();
+    let mut attempt = 1;
+
+    loop {
+        record_attempt(attempt > 1);
+        let last_attempt = attempt >= policy.max_attempts;
+
+        let sent = tokio::select! {
+            sent = build().send() => sent,
+            _ = CANCEL.notified() => return Err(AppError::Cancelled),
+        };
+
+        let delay = match sent {
+            Ok(response) => match retry_delay(&response, attempt, &policy) {
+                Some(delay) if !last_attempt => delay,
+                _ => return Ok(response),
+            },
+            Err(error) if is_transient(&error) && !last_attempt => backoff(attempt, &policy),
+            Err(error) => return Err(error.into()),
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = CANCEL.notified() => return Err(AppError::Cancelled),
+        }
+
+        attempt += 1;
+    }
+}
+
+/// Delay before retrying `response`, or `None` if it shouldn't be retried.
+/// A `Retry-After` longer than the cap isn't worth waiting for here.
+fn retry_delay(
+    response: &reqwest::Response,
+    attempt: u32,
+    policy: &RetryPolicy,
+) -> Option<Duration> {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+
+    let transient = status.is_server_error()
+        || status.as_u16() == 429
+        // Secondary rate limits answer 403 with a `Retry-After`
+        || (status.as_u16() == 403 && retry_after.is_some());
+    if !transient {
+        return None;
+    }
+
+    match retry_after {
+        Some(delay) if delay > Duration::from_millis(policy.max_delay_ms) => None,
+        Some(delay) => Some(delay),
+        None => Some(backoff(attempt, policy)),
+    }
+}
+
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout()
+}
+
+/// `base * 2^(attempt - 1)` capped at `max_delay_ms`, with "equal jitter":
+/// a random point in the upper half of that delay
+fn backoff(attempt: u32, policy: &RetryPolicy) -> Duration {
+    let exponential = policy
+        .base_delay_ms
+        .saturating_mul(1u64 << (attempt - 1).min(16));
+    let delay = exponential.min(policy.max_delay_ms);
+    let half = delay / 2;
+    Duration::from_millis(half + random_below(delay - half + 1))
+}
+
+/// Random value in `0..bound`, seeded per call by the std hasher keys
+fn random_below(bound: u64) -> u64 {
+    RandomState::new().build_hasher().finish() % bound.max(1)
+}
+
+fn record_attempt(retry: bool) {
+    let _ = ATTEMPTS.try_with(|count| {
+        let mut current = count.get();
+        current.attempts += 1;
+        if retry {
+            current.retries += 1;
+        }
+        count.set(current);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn cancel_aborts_a_request_in_flight() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                open.push(stream);
+            }
+        });
+
+        let client = client();
+        let request = tokio::spawn(async move { send(|| client.get(&url)).await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        cancel_pending();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), request)
+            .await
+            .expect("the request was not cancelled")
+            .unwrap();
+        assert_eq!(result.unwrap_err(), AppError::Cancelled);
+    }
+}
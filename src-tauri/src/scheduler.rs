@@ -0,0 +1,81 @@
+use crate::commands;
+use crate::error::AppError;
+use crate::settings;
+use chrono::Utc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::Notify;
+
+/// Default seconds between background refreshes of the active account
+pub const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+/// Shortest interval accepted by `set_interval`
+pub const MIN_INTERVAL_SECS: u64 = 60;
+
+/// How often an offline scheduler checks whether the network is back
+const OFFLINE_RETRY_SECS: i64 = 60;
+
+/// Granularity of the loop. Tokio's clock stops while the machine sleeps,
+/// so due times are wall-clock and checked this often; a refresh that came
+/// due during a suspend then runs within one tick of resuming.
+const TICK: Duration = Duration::from_secs(15);
+
+static INTERVAL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_INTERVAL_SECS);
+
+/// Wakes the loop early when the interval changes
+static WAKE: Notify = Notify::const_new();
+
+pub fn interval() -> u64 {
+    INTERVAL_SECS.load(Ordering::Relaxed)
+}
+
+pub fn set_interval(seconds: u64) {
+    INTERVAL_SECS.store(seconds.max(MIN_INTERVAL_SECS), Ordering::Relaxed);
+    WAKE.notify_waiters();
+}
+
+/// Start refreshing the active account's calendar every `interval()`
+/// seconds, emitting `contributions-updated` whenever it changed
+pub fn start(app_handle: tauri::AppHandle) {
+    if let Ok(settings) = settings::load(&app_handle) {
+        set_interval(settings.refresh_interval_secs);
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut last_run = Utc::now().timestamp();
+        let mut next_due = last_run + interval() as i64;
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(TICK) => {}
+                _ = WAKE.notified() => {
+                    next_due = last_run + interval() as i64;
+                }
+            }
+
+            let now = Utc::now().timestamp();
+            if now < next_due {
+                continue;
+            }
+            last_run = now;
+
+            next_due = match commands::refresh_active_account(&app_handle).await {
+                Ok(Some(update)) => {
+                    let _ = app_handle.emit("contributions-updated", update);
+                    now + interval() as i64
+                }
+                Ok(None) => now + interval() as i64,
+                // Paused until the budget resets
+                Err(AppError::RateLimited {
+                    reset_at: Some(reset_at),
+                }) => reset_at.max(now + OFFLINE_RETRY_SECS),
+                // Paused, checking back sooner than a full interval
+                Err(AppError::Offline { .. } | AppError::RateLimited { reset_at: None }) => {
+                    now + OFFLINE_RETRY_SECS.min(interval() as i64)
+                }
+                Err(_) => now + interval() as i64,
+            };
+        }
+    });
+}
@@ -0,0 +1,297 @@
+use crate::accounts::{self, AccountIndex};
+use crate::error::AppError;
+use crate::levels::LevelScale;
+use crate::scheduler;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use tauri::{Emitter, Manager};
+
+/// Version written to `settings.json`; bump it and extend `migrate` when a
+/// field changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Longest accepted cache TTL (one day)
+const MAX_CACHE_TTL_SECS: u64 = 86_400;
+
+/// Held from `load` to `save` in `update`, so two commands changing
+/// different fields can't overwrite each other's changes
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    #[default]
+    Sunday,
+    Monday,
+}
+
+/// Everything the app persists except tokens and cached data, stored as
+/// `settings.json` in the app config dir. Missing fields take their
+/// defaults, so the file can be trimmed or edited by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32,
+    /// Signed-in accounts and which one is active (formerly `accounts.json`)
+    #[serde(flatten)]
+    pub accounts: AccountIndex,
+    /// How long cached data is served without refreshing it
+    pub cache_ttl_secs: u64,
+    /// Seconds between background refreshes of the active account
+    pub refresh_interval_secs: u64,
+    pub theme: Theme,
+    pub week_start: WeekStart,
+    /// How counts map to color levels
+    pub level_scale: LevelScale,
+    /// Smallest count shown at levels 1 through 4 with the fixed scale
+    pub level_thresholds: [u32; 4],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SCHEMA_VERSION,
+            accounts: AccountIndex::default(),
+            cache_ttl_secs: 300,
+            refresh_interval_secs: scheduler::DEFAULT_INTERVAL_SECS,
+            theme: Theme::default(),
+            week_start: WeekStart::default(),
+            level_scale: LevelScale::default(),
+            level_thresholds: [1, 3, 6, 9],
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.cache_ttl_secs > MAX_CACHE_TTL_SECS {
+            return Err(AppError::invalid_input(format!(
+                "cacheTtlSecs must be at most {} seconds",
+                MAX_CACHE_TTL_SECS
+            )));
+        }
+
+        if self.refresh_interval_secs < scheduler::MIN_INTERVAL_SECS {
+            return Err(AppError::invalid_input(format!(
+                "refreshIntervalSecs must be at least {} seconds",
+                scheduler::MIN_INTERVAL_SECS
+            )));
+        }
+
+        if !thresholds_valid(&self.level_thresholds) {
+            return Err(AppError::invalid_input(
+                "levelThresholds must be increasing and start at 1 or more",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Bring out-of-range values (from a hand-edited file) back into range,
+    /// returning the names of the fields that were changed
+    fn clamp(&mut self) -> Vec<&'static str> {
+        let mut clamped = Vec::new();
+
+        if self.cache_ttl_secs > MAX_CACHE_TTL_SECS {
+            self.cache_ttl_secs = MAX_CACHE_TTL_SECS;
+            clamped.push("cacheTtlSecs");
+        }
+
+        if self.refresh_interval_secs < scheduler::MIN_INTERVAL_SECS {
+            self.refresh_interval_secs = scheduler::MIN_INTERVAL_SECS;
+            clamped.push("refreshIntervalSecs");
+        }
+
+        if !thresholds_valid(&self.level_thresholds) {
+            self.level_thresholds = Settings::default().level_thresholds;
+            clamped.push("levelThresholds");
+        }
+
+        clamped
+    }
+}
+
+fn thresholds_valid(thresholds: &[u32; 4]) -> bool {
+    thresholds[0] > 0 && thresholds.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// Preferences changed by `update_settings`; fields left out keep their value.
+/// Accounts are changed through the account commands instead.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsUpdate {
+    pub cache_ttl_secs: Option<u64>,
+    pub refresh_interval_secs: Option<u64>,
+    pub theme: Option<Theme>,
+    pub week_start: Option<WeekStart>,
+    pub level_scale: Option<LevelScale>,
+    pub level_thresholds: Option<[u32; 4]>,
+}
+
+impl SettingsUpdate {
+    pub fn apply(self, settings: &mut Settings) {
+        if let Some(cache_ttl_secs) = self.cache_ttl_secs {
+            settings.cache_ttl_secs = cache_ttl_secs;
+        }
+        if let Some(refresh_interval_secs) = self.refresh_interval_secs {
+            settings.refresh_interval_secs = refresh_interval_secs;
+        }
+        if let Some(theme) = self.theme {
+            settings.theme = theme;
+        }
+        if let Some(week_start) = self.week_start {
+            settings.week_start = week_start;
+        }
+        if let Some(level_scale) = self.level_scale {
+            settings.level_scale = level_scale;
+        }
+        if let Some(level_thresholds) = self.level_thresholds {
+            settings.level_thresholds = level_thresholds;
+        }
+    }
+}
+
+/// Read `settings.json`, creating it on first run from `accounts.json`.
+/// A file written by a newer version of the app is refused rather than
+/// silently downgraded. Out-of-range values are clamped and the corrected
+/// file written back, so a bad hand edit can't make every later `save` fail.
+pub fn load(app_handle: &tauri::AppHandle) -> Result<Settings, AppError> {
+    let path = get_settings_path(app_handle)?;
+
+    if !path.exists() {
+        let settings = Settings {
+            accounts: accounts::load_legacy_index(app_handle)?,
+            ..Settings::default()
+        };
+        write(&settings, &path)?;
+        return Ok(settings);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let value: serde_json::Value = serde_json::from_str(&content)?;
+    let version = value["version"].as_u64().unwrap_or(0);
+
+    if version > SCHEMA_VERSION as u64 {
+        return Err(AppError::storage(format!(
+            "settings.json has schema version {}, newer than this app supports ({})",
+            version, SCHEMA_VERSION
+        )));
+    }
+
+    let mut settings = migrate(value, version)?;
+    let clamped = settings.clamp();
+    if !clamped.is_empty() {
+        eprintln!(
+            "settings.json: out-of-range {} reset to valid values",
+            clamped.join(", ")
+        );
+        write(&settings, &path)?;
+    }
+
+    Ok(settings)
+}
+
+/// Upgrade settings written with an older schema `version`
+fn migrate(value: serde_json::Value, version: u64) -> Result<Settings, AppError> {
+    let mut settings: Settings = serde_json::from_value(value)?;
+
+    // Version 0 is a hand-written file without a version; its fields
+    // already mean what version 1's do
+    if version < SCHEMA_VERSION as u64 {
+        settings.version = SCHEMA_VERSION;
+    }
+
+    Ok(settings)
+}
+
+/// Load the settings, apply `change` and save them, with no other update in
+/// between. Nothing is written if `change` fails.
+pub fn update<T>(
+    app_handle: &tauri::AppHandle,
+    change: impl FnOnce(&mut Settings) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    let _guard = UPDATE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let mut settings = load(app_handle)?;
+    let result = change(&mut settings)?;
+    save(&settings, app_handle)?;
+
+    Ok(result)
+}
+
+/// Validate and persist `settings`, apply what takes effect immediately and
+/// emit `settings-changed` with the new settings
+fn save(settings: &Settings, app_handle: &tauri::AppHandle) -> Result<(), AppError> {
+    settings.validate()?;
+    write(settings, &get_settings_path(app_handle)?)?;
+
+    scheduler::set_interval(settings.refresh_interval_secs);
+    let _ = app_handle.emit("settings-changed", settings.clone());
+
+    Ok(())
+}
+
+fn write(settings: &Settings, path: &Path) -> Result<(), AppError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Write a temp file and rename it over the old one, so a crash
+    // mid-write can't leave a truncated settings.json behind
+    let json = serde_json::to_string_pretty(settings)?;
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, json)?;
+    fs::rename(&temp_path, path).map_err(AppError::from)
+}
+
+fn get_settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_config_dir = app_handle
+        .path()
+        .app_config_dir()
+        .map_err(|e| AppError::storage(format!("Failed to get app config dir: {}", e)))?;
+
+    Ok(app_config_dir.join("settings.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_keeps_valid_settings() {
+        let mut settings = Settings::default();
+        assert!(settings.clamp().is_empty());
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn clamp_fixes_and_reports_each_bad_field() {
+        let mut settings: Settings = serde_json::from_str(
+            r#"{"version":1,"cacheTtlSecs":999999,"refreshIntervalSecs":5,"levelThresholds":[0,2,2,5]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.clamp(),
+            vec!["cacheTtlSecs", "refreshIntervalSecs", "levelThresholds"]
+        );
+        assert_eq!(settings.cache_ttl_secs, MAX_CACHE_TTL_SECS);
+        assert_eq!(settings.refresh_interval_secs, scheduler::MIN_INTERVAL_SECS);
+        assert_eq!(
+            settings.level_thresholds,
+            Settings::default().level_thresholds
+        );
+        assert!(settings.validate().is_ok());
+    }
+}
@@ -0,0 +1,332 @@
+use crate::commands::ContributionDay;
+use crate::error::AppError;
+use crate::settings::WeekStart;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A run of consecutive days with contributions; dates are `None` when
+/// `length` is 0
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Streak {
+    pub length: u32,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+/// The day, week or month with the most contributions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Peak {
+    pub start: String,
+    pub end: String,
+    pub contribution_count: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekdayTotal {
+    pub contribution_count: i64,
+    pub active_days: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContributionStats {
+    pub total: i64,
+    pub active_days: u32,
+    /// Ends today, or yesterday while today has no contributions yet
+    pub current_streak: Streak,
+    pub longest_streak: Streak,
+    pub busiest_day: Option<Peak>,
+    pub busiest_week: Option<Peak>,
+    pub busiest_month: Option<Peak>,
+    /// Mean and median count over active days only
+    pub mean_active: f64,
+    pub median_active: f64,
+    /// Totals per weekday, Sunday first
+    pub weekdays: [WeekdayTotal; 7],
+}
+
+/// Summarize `days` as seen on `today` in the user's timezone. Missing
+/// dates count as zero, repeated dates are summed and days after `today`
+/// are ignored.
+pub fn compute(
+    days: &[ContributionDay],
+    today: NaiveDate,
+    week_start: WeekStart,
+) -> Result<ContributionStats, AppError> {
+    let mut counts: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    for day in days {
+        let date = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d")
+            .map_err(|e| AppError::invalid_input(format!("Invalid date '{}': {}", day.date, e)))?;
+        if date <= today {
+            *counts.entry(date).or_insert(0) += i64::from(day.contribution_count);
+        }
+    }
+
+    let mut active: Vec<i64> = counts
+        .values()
+        .copied()
+        .filter(|count| *count > 0)
+        .collect();
+    active.sort_unstable();
+
+    let mut weekdays = [WeekdayTotal::default(); 7];
+    let mut by_week: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut by_month: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+
+    for (&date, &count) in &counts {
+        let weekday = &mut weekdays[date.weekday().num_days_from_sunday() as usize];
+        weekday.contribution_count += count;
+        if count > 0 {
+            weekday.active_days += 1;
+        }
+
+        *by_week.entry(week_of(date, week_start)).or_insert(0) += count;
+        *by_month
+            .entry(date.with_day(1).unwrap_or(date))
+            .or_insert(0) += count;
+    }
+
+    let total = counts.values().sum();
+    let mean_active = if active.is_empty() {
+        0.0
+    } else {
+        active.iter().sum::<i64>() as f64 / active.len() as f64
+    };
+
+    Ok(ContributionStats {
+        total,
+        active_days: active.len() as u32,
+        current_streak: current_streak(&counts, today),
+        longest_streak: longest_streak(&counts),
+        busiest_day: busiest(&counts, |date| date),
+        busiest_week: busiest(&by_week, |start| start + Days::new(6)),
+        busiest_month: busiest(&by_month, |start| start + Months::new(1) - Days::new(1)),
+        mean_active,
+        median_active: median(&active),
+        weekdays,
+    })
+}
+
+/// The streak running through today, or through yesterday when today has
+/// nothing yet so a streak isn't broken before the day is over
+fn current_streak(counts: &BTreeMap<NaiveDate, i64>, today: NaiveDate) -> Streak {
+    let is_active = |date: NaiveDate| counts.get(&date).is_some_and(|count| *count > 0);
+
+    let end = if is_active(today) {
+        today
+    } else {
+        match today.pred_opt().filter(|yesterday| is_active(*yesterday)) {
+            Some(yesterday) => yesterday,
+            None => return Streak::default(),
+        }
+    };
+
+    let mut start = end;
+    while let Some(previous) = start.pred_opt().filter(|date| is_active(*date)) {
+        start = previous;
+    }
+
+    streak(start, end)
+}
+
+/// The longest run of active days; the earliest wins a tie
+fn longest_streak(counts: &BTreeMap<NaiveDate, i64>) -> Streak {
+    let mut longest = Streak::default();
+    let mut run: Option<(NaiveDate, NaiveDate)> = None;
+
+    for (&date, &count) in counts {
+        if count <= 0 {
+            run = None;
+            continue;
+        }
+
+        let start = match run {
+            Some((start, end)) if end.succ_opt() == Some(date) => start,
+            _ => date,
+        };
+        run = Some((start, date));
+
+        let current = streak(start, date);
+        if current.length > longest.length {
+            longest = current;
+        }
+    }
+
+    longest
+}
+
+fn streak(start: NaiveDate, end: NaiveDate) -> Streak {
+    Streak {
+        length: (end - start).num_days() as u32 + 1,
+        start: Some(start.to_string()),
+        end: Some(end.to_string()),
+    }
+}
+
+/// Highest-count entry of `totals` keyed by period start, or `None` when
+/// there are no contributions; the earliest wins a tie
+fn busiest(
+    totals: &BTreeMap<NaiveDate, i64>,
+    end_of: impl Fn(NaiveDate) -> NaiveDate,
+) -> Option<Peak> {
+    let mut best: Option<(NaiveDate, i64)> = None;
+
+    for (&start, &count) in totals {
+        if count > best.map_or(0, |(_, best_count)| best_count) {
+            best = Some((start, count));
+        }
+    }
+
+    best.map(|(start, contribution_count)| Peak {
+        start: start.to_string(),
+        end: end_of(start).to_string(),
+        contribution_count,
+    })
+}
+
+fn median(sorted: &[i64]) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        len if len % 2 == 1 => sorted[len / 2] as f64,
+        len => (sorted[len / 2 - 1] + sorted[len / 2]) as f64 / 2.0,
+    }
+}
+
+/// First day of the week containing `date`
+pub fn week_of(date: NaiveDate, week_start: WeekStart) -> NaiveDate {
+    let offset = match week_start {
+        WeekStart::Sunday => date.weekday().num_days_from_sunday(),
+        WeekStart::Monday => date.weekday().num_days_from_monday(),
+    };
+    date - Days::new(u64::from(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn summarize(counts: &[(&str, i32)], today: &str, week_start: WeekStart) -> ContributionStats {
+        let days: Vec<ContributionDay> = counts
+            .iter()
+            .map(|(date, count)| ContributionDay::new(date.to_string(), *count))
+            .collect();
+        compute(&days, date(today), week_start).unwrap()
+    }
+
+    fn streak_of(length: u32, start: &str, end: &str) -> Streak {
+        Streak {
+            length,
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+        }
+    }
+
+    #[test]
+    fn current_streak_includes_an_active_today() {
+        let counts = [("2024-03-08", 1), ("2024-03-09", 2), ("2024-03-10", 1)];
+        let stats = summarize(&counts, "2024-03-10", WeekStart::Sunday);
+        assert_eq!(
+            stats.current_streak,
+            streak_of(3, "2024-03-08", "2024-03-10")
+        );
+    }
+
+    #[test]
+    fn current_streak_ends_yesterday_while_today_is_empty() {
+        let counts = [("2024-03-08", 1), ("2024-03-09", 2), ("2024-03-10", 0)];
+        let stats = summarize(&counts, "2024-03-10", WeekStart::Sunday);
+        assert_eq!(
+            stats.current_streak,
+            streak_of(2, "2024-03-08", "2024-03-09")
+        );
+    }
+
+    #[test]
+    fn current_streak_stops_at_a_gap() {
+        let counts = [
+            ("2024-03-06", 4),
+            ("2024-03-07", 0),
+            ("2024-03-08", 1),
+            ("2024-03-09", 1),
+        ];
+        let stats = summarize(&counts, "2024-03-09", WeekStart::Sunday);
+        assert_eq!(
+            stats.current_streak,
+            streak_of(2, "2024-03-08", "2024-03-09")
+        );
+
+        // Nothing today or yesterday breaks the streak
+        let stats = summarize(&counts, "2024-03-11", WeekStart::Sunday);
+        assert_eq!(stats.current_streak, Streak::default());
+    }
+
+    #[test]
+    fn ties_go_to_the_earliest_streak_and_day() {
+        let counts = [
+            ("2024-03-01", 5),
+            ("2024-03-02", 1),
+            ("2024-03-03", 0),
+            ("2024-03-10", 1),
+            ("2024-03-11", 5),
+        ];
+        let stats = summarize(&counts, "2024-03-20", WeekStart::Sunday);
+
+        assert_eq!(
+            stats.longest_streak,
+            streak_of(2, "2024-03-01", "2024-03-02")
+        );
+        assert_eq!(
+            stats.busiest_day,
+            Some(Peak {
+                start: "2024-03-01".to_string(),
+                end: "2024-03-01".to_string(),
+                contribution_count: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn busiest_week_follows_the_week_start() {
+        // Sunday the 3rd, then Monday the 4th and Tuesday the 5th
+        let counts = [("2024-03-03", 4), ("2024-03-04", 3), ("2024-03-05", 3)];
+
+        let sunday = summarize(&counts, "2024-03-10", WeekStart::Sunday);
+        assert_eq!(
+            sunday.busiest_week,
+            Some(Peak {
+                start: "2024-03-03".to_string(),
+                end: "2024-03-09".to_string(),
+                contribution_count: 10,
+            })
+        );
+
+        let monday = summarize(&counts, "2024-03-10", WeekStart::Monday);
+        assert_eq!(
+            monday.busiest_week,
+            Some(Peak {
+                start: "2024-03-04".to_string(),
+                end: "2024-03-10".to_string(),
+                contribution_count: 6,
+            })
+        );
+    }
+
+    #[test]
+    fn median_of_odd_and_even_lengths() {
+        assert_eq!(median(&[]), 0.0);
+        assert_eq!(median(&[1, 3, 8]), 3.0);
+        assert_eq!(median(&[1, 3, 8, 10]), 5.5);
+
+        let counts = [("2024-03-01", 8), ("2024-03-02", 0), ("2024-03-03", 1)];
+        let stats = summarize(&counts, "2024-03-03", WeekStart::Sunday);
+        assert_eq!(stats.median_active, 4.5);
+    }
+}
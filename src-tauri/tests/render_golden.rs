@@ -0,0 +1,133 @@
+//! Golden-image tests for the PNG export. Images are rendered with the
+//! bundled font, as the export does by default, so they match on every
+//! machine. A missing golden image fails the test; set
+//! `UPDATE_GOLDEN=1` to record it, or to re-record after an intended change.
+//! On a mismatch the output is saved next to the golden image as
+//! `*.actual.png`.
+
+use chrono::{Days, NaiveDate};
+use github_widget::render::{render_png, PngOptions, RenderOptions, RenderTheme};
+use github_widget::{ContributionDay, WeekStart};
+use resvg::tiny_skia::Pixmap;
+use std::path::PathBuf;
+
+/// Largest per-channel difference tolerated, for anti-aliasing changes
+/// between rasterizer releases
+const TOLERANCE: u8 = 2;
+
+/// The default output options at `scale`
+fn png_options(scale: f32) -> PngOptions {
+    PngOptions {
+        scale,
+        ..PngOptions::default()
+    }
+}
+
+/// Twelve weeks from a Sunday with a repeating, uneven pattern of counts
+fn fixture() -> Vec<ContributionDay> {
+    let start = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+    (0..84u64)
+        .map(|offset| {
+            let date = start + Days::new(offset);
+            let count = ((offset * 7) % 11) as i32 - 3;
+            ContributionDay::new(date.to_string(), count.max(0))
+        })
+        .collect()
+}
+
+fn check_golden(name: &str, options: &RenderOptions, scale: f32) {
+    let png = render_png(&fixture(), options, None, &png_options(scale)).expect("render failed");
+
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let golden_path = dir.join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&golden_path, &png).unwrap();
+        return;
+    }
+
+    assert!(
+        golden_path.exists(),
+        "{} is missing; run with UPDATE_GOLDEN=1 to record it",
+        golden_path.display()
+    );
+
+    let actual = Pixmap::decode_png(&png).unwrap();
+    let expected = Pixmap::decode_png(&std::fs::read(&golden_path).unwrap()).unwrap();
+
+    let matches = actual.width() == expected.width()
+        && actual.height() == expected.height()
+        && actual
+            .data()
+            .iter()
+            .zip(expected.data())
+            .all(|(a, b)| a.abs_diff(*b) <= TOLERANCE);
+
+    if !matches {
+        let actual_path = dir.join(format!("{}.actual.png", name));
+        std::fs::write(&actual_path, &png).unwrap();
+        panic!(
+            "{} differs from {}; compare with {}",
+            name,
+            golden_path.display(),
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn light_theme_at_1x() {
+    let options = RenderOptions {
+        theme: RenderTheme::Light,
+        ..RenderOptions::default()
+    };
+    check_golden("heatmap_light_1x", &options, 1.0);
+}
+
+#[test]
+fn dark_theme_at_2x() {
+    let options = RenderOptions {
+        theme: RenderTheme::Dark,
+        ..RenderOptions::default()
+    };
+    check_golden("heatmap_dark_2x", &options, 2.0);
+}
+
+#[test]
+fn monday_week_start() {
+    let options = RenderOptions {
+        week_start: WeekStart::Monday,
+        ..RenderOptions::default()
+    };
+    check_golden("heatmap_monday_1x", &options, 1.0);
+}
+
+#[test]
+fn scale_is_applied_to_the_image_size() {
+    let render = |scale| {
+        let png = render_png(
+            &fixture(),
+            &RenderOptions::default(),
+            None,
+            &png_options(scale),
+        )
+        .unwrap();
+        Pixmap::decode_png(&png).unwrap()
+    };
+
+    let (small, large) = (render(1.0), render(3.0));
+    assert_eq!(large.width(), small.width() * 3);
+    assert_eq!(large.height(), small.height() * 3);
+}
+
+#[test]
+fn out_of_range_scale_is_rejected() {
+    let result = render_png(
+        &fixture(),
+        &RenderOptions::default(),
+        None,
+        &png_options(0.0),
+    );
+    assert_eq!(result.unwrap_err().code(), "INVALID_INPUT");
+}
@@ -1,31 +1,39 @@
-import { useState, useEffect, useCallback } from 'react';
+import { useState, useEffect, useCallback, useRef } from 'react';
 import Heatmap from './components/Heatmap';
 import Login from './components/Login';
-import { fetchContributionsViaTauri, getGitHubToken, deleteGitHubToken } from './services/tauri-api';
+import { fetchContributionsViaTauri, getTokenStatus, addAccount, removeAccount, deleteGitHubToken, getAccountHost, onCacheRefreshed, onContributionsUpdated, getSettings, onSettingsChanged, computeContributionStats } from './services/tauri-api';
 import { fetchGitHubActivity } from './services/github';
 
 import { getCurrentWindow } from '@tauri-apps/api/window';
 
+// The stylesheets only know light and dark; 'system' follows the OS
+const LIGHT_SCHEME = '(prefers-color-scheme: light)';
+
 function App() {
   const [activity, setActivity] = useState([]);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState(null);
   const [isAuthenticated, setIsAuthenticated] = useState(false);
+  const [settings, setSettings] = useState(null);
+  const [stats, setStats] = useState(null);
+  const [systemLight, setSystemLight] = useState(() => window.matchMedia?.(LIGHT_SCHEME).matches ?? false);
+  const hasActivity = useRef(false);
 
   const loadActivity = useCallback(async () => {
     try {
       setLoading(true);
-      // Get credentials
-      const username = localStorage.getItem('github_username');
-      let token = await getGitHubToken();
-      
-      // Fallback to env/localstorage if not in keychain (migration/dev)
-      if (!token) {
-        token = localStorage.getItem('github_token') || 
-                import.meta.env.VITE_GITHUB_TOKEN;
+      // The backend reads the active account's token from the keychain;
+      // only its status crosses over to the webview
+      let status = await getTokenStatus();
+
+      // Accounts signed in before the account index existed
+      const legacyUsername = localStorage.getItem('github_username');
+      if (!status.account && legacyUsername) {
+        await addAccount({ login: legacyUsername });
+        status = await getTokenStatus();
       }
 
-      if (!username || !token) {
+      if (!status.account || !status.hasToken) {
         setIsAuthenticated(false);
         setLoading(false);
         return;
@@ -35,20 +43,42 @@ function App() {
 
       // Try Tauri backend first
       try {
-        const data = await fetchContributionsViaTauri(username, token);
+        const data = await fetchContributionsViaTauri();
         setActivity(data);
+        hasActivity.current = true;
         setError(null);
       } catch (tauri_error) {
+        // Errors with a code come from the backend itself; only fall back
+        // when the Tauri bridge is unavailable
+        if (tauri_error.code) {
+          throw tauri_error;
+        }
         console.warn('Tauri backend failed, falling back to JS:', tauri_error);
-        // Fallback
-        const data = await fetchGitHubActivity(username, token);
+        // Fallback (dev builds only have a token in env/localStorage)
+        const data = await fetchGitHubActivity(status.account.login);
         setActivity(data);
         setError(null);
       }
 
     } catch (error) {
       console.error('Failed to fetch GitHub activity:', error);
-      setError(error.message);
+      switch (error.code) {
+        case 'UNAUTHORIZED':
+        case 'NOT_SIGNED_IN':
+        case 'CREDENTIALS_LOCKED':
+          // Ask for a new token (or the passphrase) instead of showing an error
+          setIsAuthenticated(false);
+          break;
+        case 'OFFLINE':
+        case 'RATE_LIMITED':
+          // Transient: keep showing the last heatmap, retry on the next refresh
+          if (!hasActivity.current) {
+            setError(error.message);
+          }
+          break;
+        default:
+          setError(error.message);
+      }
     } finally {
       setLoading(false);
     }
@@ -56,8 +86,63 @@ function App() {
 
   useEffect(() => {
     loadActivity();
-    const interval = setInterval(loadActivity, 60000); // Refresh every minute
-    return () => clearInterval(interval);
+  }, [loadActivity]);
+
+  useEffect(() => {
+    getSettings().then(setSettings).catch((e) => console.warn('Failed to load settings:', e));
+    const unlisten = onSettingsChanged(setSettings).catch(() => null);
+    return () => {
+      unlisten.then((stop) => stop && stop());
+    };
+  }, []);
+
+  useEffect(() => {
+    const query = window.matchMedia?.(LIGHT_SCHEME);
+    if (!query) {
+      return undefined;
+    }
+    const onChange = (event) => setSystemLight(event.matches);
+    query.addEventListener('change', onChange);
+    return () => query.removeEventListener('change', onChange);
+  }, []);
+
+  const theme = settings?.theme === 'light' || (settings?.theme !== 'dark' && systemLight) ? 'light' : 'dark';
+
+  useEffect(() => {
+    if (activity.length === 0) {
+      setStats(null);
+      return;
+    }
+    computeContributionStats(activity)
+      .then(setStats)
+      .catch((e) => {
+        console.warn('Failed to compute stats:', e);
+        setStats(null);
+      });
+  }, [activity]);
+
+  // The backend refreshes on its own schedule and only reports changes
+  useEffect(() => {
+    const unlisten = onContributionsUpdated((update) => {
+      setActivity(update.days);
+      hasActivity.current = true;
+      setError(null);
+    }).catch(() => null);
+    return () => {
+      unlisten.then((stop) => stop && stop());
+    };
+  }, []);
+
+  // A stale cache is shown immediately; reload once its refresh lands
+  useEffect(() => {
+    const unlisten = onCacheRefreshed((refresh) => {
+      if (refresh.ok) {
+        loadActivity();
+      }
+    }).catch(() => null);
+    return () => {
+      unlisten.then((stop) => stop && stop());
+    };
   }, [loadActivity]);
 
 
@@ -68,11 +153,17 @@ function App() {
 
   const handleLogout = async () => {
     try {
-      await deleteGitHubToken();
+      const { account } = await getTokenStatus();
+      if (account) {
+        await removeAccount(account.id);
+      }
+      const username = localStorage.getItem('github_username');
+      await deleteGitHubToken(username ? await getAccountHost(username) : null);
       localStorage.removeItem('github_username');
       localStorage.removeItem('github_token');
       setIsAuthenticated(false);
       setActivity([]);
+      hasActivity.current = false;
     } catch (e) {
       console.error('Failed to logout:', e);
     }
@@ -88,7 +179,7 @@ function App() {
 
   if (!isAuthenticated && !loading) {
     return (
-      <div className="app">
+      <div className="app" data-theme={theme}>
         <div className="drag-region" data-tauri-drag-region />
         <div className="window-controls">
           <button className="control-btn close-btn" onClick={handleClose} title="Close Widget">×</button>
@@ -99,7 +190,7 @@ function App() {
   }
 
   return (
-    <div className="app">
+    <div className="app" data-theme={theme}>
       <div className="drag-region" data-tauri-drag-region />
       <div className="window-controls">
         {isAuthenticated && (
@@ -130,7 +221,7 @@ function App() {
           </button>
         </div>
       ) : (
-        <Heatmap data={activity} />
+        <Heatmap data={activity} stats={stats} thresholds={settings?.levelThresholds} weekStart={settings?.weekStart} />
       )}
     </div>
   );
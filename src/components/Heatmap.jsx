@@ -1,6 +1,6 @@
 import './heatmap.css';
 
-function Heatmap({ data }) {
+function Heatmap({ data, stats = null, thresholds = [1, 3, 6, 9], weekStart = 'sunday' }) {
   if (!data || data.length === 0) {
     return (
       <div className="heatmap">
@@ -9,17 +9,24 @@ function Heatmap({ data }) {
     );
   }
 
-  // Calculate total contributions
-  const totalContributions = data.reduce((sum, day) => sum + day.contributionCount, 0);
+  // Totals come from the backend; summing here covers the JS fallback
+  const totalContributions = stats
+    ? stats.total
+    : data.reduce((sum, day) => sum + day.contributionCount, 0);
 
   // Ensure we have at least 365 days for a full year view, or use available data
   // GitHub usually shows the last year (52-53 weeks)
   const recentData = data;
   
+  // Pad the first column so every column starts on the week start day
+  const firstWeekday = weekStart === 'monday' ? 1 : 0;
+  const leading = (new Date(recentData[0].date).getUTCDay() - firstWeekday + 7) % 7;
+  const paddedData = [...Array(leading).fill(null), ...recentData];
+
   // Group days into weeks (7 days each)
   const weeks = [];
-  for (let i = 0; i < recentData.length; i += 7) {
-    weeks.push(recentData.slice(i, i + 7));
+  for (let i = 0; i < paddedData.length; i += 7) {
+    weeks.push(paddedData.slice(i, i + 7));
   }
 
   // Get month labels for the display
@@ -33,8 +40,9 @@ function Heatmap({ data }) {
   const monthLabels = [];
   let currentMonth = '';
   weeks.forEach((week, weekIndex) => {
-    if (week[0]) {
-      const month = getMonthLabel(week[0].date);
+    const firstDay = week.find(Boolean);
+    if (firstDay) {
+      const month = getMonthLabel(firstDay.date);
       if (month !== currentMonth) {
         // Only add label if it's far enough from the previous one (approx 4 weeks)
         // GitHub's logic is a bit more complex but this approximates it
@@ -50,10 +58,15 @@ function Heatmap({ data }) {
     <div className="heatmap-container">
       <div className="heatmap-header">
         <span>{totalContributions} contributions in the last year</span>
+        {stats && stats.currentStreak.length > 0 && (
+          <span className="heatmap-streak">
+            {stats.currentStreak.length}-day streak (best {stats.longestStreak.length})
+          </span>
+        )}
       </div>
       
       <div className="heatmap-body">
-        <div className="day-labels">
+        <div className={`day-labels starts-${weekStart}`}>
           <span className="day-label">Mon</span>
           <span className="day-label">Wed</span>
           <span className="day-label">Fri</span>
@@ -76,17 +89,19 @@ function Heatmap({ data }) {
             {weeks.map((week, weekIndex) => (
               <div key={weekIndex} className="heatmap-week">
                 {week.map((day, dayIndex) => {
+                  if (!day) {
+                    return <div key={dayIndex} className="heatmap-day empty" />;
+                  }
                   const count = day.contributionCount;
-                  const level = count === 0 ? 0 : 
-                               count < 3 ? 1 : 
-                               count < 6 ? 2 : 
-                               count < 9 ? 3 : 4;
+                  // The backend levels days with the configured scale; the
+                  // thresholds only cover data from the JS fallback
+                  const level = day.contributionLevel ?? thresholds.filter((min) => count >= min).length;
                   
                   return (
                     <div
                       key={dayIndex}
                       className={`heatmap-day level-${level}`}
-                      data-tooltip={day ? `${day.contributionCount} contributions on ${day.date}` : ''}
+                      data-tooltip={`${day.contributionCount} contributions on ${day.date}`}
                     />
                   );
                 })}
@@ -1,20 +1,55 @@
 import React, { useState } from 'react';
 import { openUrl } from '@tauri-apps/plugin-opener';
-import { saveGitHubToken } from '../services/tauri-api';
+import { validateGitHubToken, addAccount, switchAccount, startDeviceLogin, completeDeviceLogin } from '../services/tauri-api';
 
 const Login = ({ onLoginSuccess }) => {
   const [token, setToken] = useState('');
+  const [host, setHost] = useState('');
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState('');
+  // Validation of the token currently in the input, kept to confirm warnings
+  const [validation, setValidation] = useState(null);
+  // Code to enter on GitHub while a browser sign-in is pending
+  const [deviceCode, setDeviceCode] = useState(null);
+  // Builds without an OAuth client id can only take pasted tokens
+  const [deviceLoginAvailable, setDeviceLoginAvailable] = useState(true);
 
   const handleConnect = async () => {
     try {
-      await openUrl('https://github.com/settings/tokens/new?scopes=repo,read:user&description=GitPulse%20Widget');
+      const baseUrl = host.trim() ? `https://${host.trim().replace(/^https?:\/\//, '').replace(/\/.*$/, '')}` : 'https://github.com';
+      await openUrl(`${baseUrl}/settings/tokens/new?scopes=read:user&description=GitPulse%20Widget`);
     } catch (err) {
       setError('Failed to open browser. Please visit GitHub settings manually.');
     }
   };
 
+  const handleDeviceLogin = async () => {
+    setLoading(true);
+    setError('');
+
+    try {
+      const hostOrNull = host.trim() || null;
+      const code = await startDeviceLogin(hostOrNull);
+      setDeviceCode(code);
+      await openUrl(code.verificationUri).catch(() => null);
+
+      // Resolves once the code is approved; the backend stores the token
+      await completeDeviceLogin(code, hostOrNull);
+      onLoginSuccess();
+    } catch (err) {
+      console.error(err);
+      if (err.reason === 'not_configured') {
+        setDeviceLoginAvailable(false);
+        setError('Browser sign-in is not available in this build. Please paste a token instead.');
+      } else {
+        setError(err.message || 'Sign-in failed. Please try again.');
+      }
+    } finally {
+      setDeviceCode(null);
+      setLoading(false);
+    }
+  };
+
   const handleSave = async (e) => {
     e.preventDefault();
     if (!token.trim()) {
@@ -26,30 +61,37 @@ const Login = ({ onLoginSuccess }) => {
     setError('');
 
     try {
-      // Verify token and get username
-      const response = await fetch('https://api.github.com/user', {
-        headers: {
-          Authorization: `Bearer ${token.trim()}`
-        }
-      });
+      // Check token, scopes and expiry (github.com or GitHub Enterprise Server)
+      let result = validation;
+      if (!result) {
+        result = await validateGitHubToken(token.trim(), host.trim() || null);
 
-      if (!response.ok) {
-        throw new Error('Invalid token or network error');
+        // Show warnings first; submitting again saves anyway
+        if (result.warnings.length > 0) {
+          setValidation(result);
+          return;
+        }
       }
 
-      const userData = await response.json();
-      const username = userData.login;
+      // Save token securely under the account's own keychain entry
+      const account = await addAccount({ login: result.login, host: host.trim() || null, token: token.trim() });
+      await switchAccount(account.id);
 
-      // Save token securely
-      await saveGitHubToken(token.trim());
-      
-      // Save username to localStorage (non-sensitive)
-      localStorage.setItem('github_username', username);
-
-      onLoginSuccess(token.trim());
+      onLoginSuccess();
     } catch (err) {
       console.error(err);
-      setError('Failed to verify token. Please check and try again.');
+      switch (err.code) {
+        case 'UNAUTHORIZED':
+          setError('GitHub rejected this token. Please check it and try again.');
+          break;
+        case 'OFFLINE':
+        case 'RATE_LIMITED':
+        case 'INVALID_INPUT':
+          setError(err.message);
+          break;
+        default:
+          setError('Failed to verify token. Please check and try again.');
+      }
     } finally {
       setLoading(false);
     }
@@ -89,6 +131,30 @@ const Login = ({ onLoginSuccess }) => {
         >
           Connect GitHub
         </button>
+        {deviceLoginAvailable && (
+          <button
+            onClick={handleDeviceLogin}
+            disabled={loading}
+            style={{
+              marginTop: '6px',
+              background: 'none',
+              color: 'inherit',
+              border: '1px solid var(--border-color)',
+              padding: '4px 10px',
+              borderRadius: '6px',
+              fontSize: '11px',
+              cursor: loading ? 'wait' : 'pointer',
+              whiteSpace: 'nowrap'
+            }}
+          >
+            Sign in with browser
+          </button>
+        )}
+        {deviceCode && (
+          <p style={{ marginTop: '5px', fontSize: '10px', lineHeight: '1.2' }}>
+            Enter <strong>{deviceCode.userCode}</strong> at {deviceCode.verificationUri}
+          </p>
+        )}
       </div>
 
       <div style={{ width: '1px', height: '80%', background: 'rgba(255,255,255,0.1)' }}></div>
@@ -101,7 +167,7 @@ const Login = ({ onLoginSuccess }) => {
           <input
             type="password"
             value={token}
-            onChange={(e) => setToken(e.target.value)}
+            onChange={(e) => { setToken(e.target.value); setValidation(null); }}
             placeholder="ghp_..."
             style={{
               flex: 1,
@@ -130,9 +196,32 @@ const Login = ({ onLoginSuccess }) => {
               whiteSpace: 'nowrap'
             }}
           >
-            {loading ? '...' : 'Save'}
+            {loading ? '...' : validation ? 'Save anyway' : 'Save'}
           </button>
         </form>
+        <input
+          type="text"
+          value={host}
+          onChange={(e) => { setHost(e.target.value); setValidation(null); }}
+          placeholder="github.com (or Enterprise Server URL)"
+          style={{
+            width: '100%',
+            marginTop: '6px',
+            padding: '4px 10px',
+            borderRadius: '6px',
+            border: '1px solid var(--border-color)',
+            background: 'var(--bg-secondary)',
+            color: 'var(--text-primary)',
+            outline: 'none',
+            fontSize: '11px',
+            boxSizing: 'border-box'
+          }}
+        />
+        {validation && validation.warnings.map((warning) => (
+          <p key={warning} style={{ color: '#d29922', fontSize: '10px', marginTop: '5px', lineHeight: '1.1' }}>
+            {warning}
+          </p>
+        ))}
         {error && (
           <p style={{ color: '#f85149', fontSize: '10px', marginTop: '5px', lineHeight: '1.1' }}>
             {error}
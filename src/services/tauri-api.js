@@ -4,29 +4,274 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 /**
- * Fetch GitHub contributions using Tauri Rust backend
- * @param {string} username - GitHub username
- * @param {string|null} token - GitHub personal access token (optional)
+ * Turn a backend error ({code, message, ...fields}) into an Error that keeps
+ * its stable `code` and fields (status, resetAt, errorType, ...), so callers
+ * can branch on `error.code`
+ * @param {Object|string|Error} error - Error from a command or a FetchResult
+ * @returns {Error}
+ */
+export function toError(error) {
+  if (error instanceof Error) {
+    return error;
+  }
+  if (error && typeof error === 'object') {
+    return Object.assign(new Error(error.message || 'Unknown error'), error);
+  }
+  return new Error(error || 'Unknown error');
+}
+
+/**
+ * Fetch contributions using Tauri Rust backend. The token is read from the
+ * OS keyring by the backend and never passes through the webview.
+ * @param {string|null} username - Account login (defaults to the active account)
  * @returns {Promise<Array>} Array of contribution days
  */
-export async function fetchContributionsViaTauri(username, token = null) {
+export async function fetchContributionsViaTauri(username = null) {
   try {
     const result = await invoke('fetch_contributions', {
-      username,
-      token: token || undefined,
+      username: username || undefined,
     });
 
     if (result.ok && result.data) {
       return result.data;
     } else {
       console.error('Tauri fetch error:', result.error);
-      throw new Error(result.error || 'Unknown error');
+      throw toError(result.error);
     }
   } catch (error) {
     console.error('Failed to fetch via Tauri:', error);
-    throw error;
+    throw toError(error);
+  }
+}
+
+/**
+ * Fetch GitHub contributions for an arbitrary date range (may span several years)
+ * @param {string|null} username - Account login (defaults to the active account)
+ * @param {string} from - Inclusive start date (YYYY-MM-DD)
+ * @param {string} to - Inclusive end date (YYYY-MM-DD)
+ * @returns {Promise<Array>} Array of contribution days
+ */
+export async function fetchContributionsRangeViaTauri(username, from, to) {
+  try {
+    const result = await invoke('fetch_contributions_range', {
+      username: username || undefined,
+      from,
+      to,
+    });
+
+    if (result.ok && result.data) {
+      return result.data;
+    } else {
+      console.error('Tauri range fetch error:', result.error);
+      throw toError(result.error);
+    }
+  } catch (error) {
+    console.error('Failed to fetch range via Tauri:', error);
+    throw toError(error);
+  }
+}
+
+/**
+ * Fetch per-day contribution counts split into commits, pull requests, issues and reviews
+ * @param {string|null} username - GitHub login (defaults to the active account)
+ * @param {string|null} from - Inclusive start date (YYYY-MM-DD), defaults to one year ago
+ * @param {string|null} to - Inclusive end date (YYYY-MM-DD), defaults to today
+ * @returns {Promise<Array>} Array of {date, commits, pullRequests, issues, reviews}
+ */
+export async function fetchContributionBreakdownViaTauri(username, from = null, to = null) {
+  try {
+    const result = await invoke('fetch_contribution_breakdown', {
+      username: username || undefined,
+      from: from || undefined,
+      to: to || undefined,
+    });
+
+    if (result.ok && result.data) {
+      return result.data;
+    } else {
+      console.error('Tauri breakdown fetch error:', result.error);
+      throw toError(result.error);
+    }
+  } catch (error) {
+    console.error('Failed to fetch breakdown via Tauri:', error);
+    throw toError(error);
+  }
+}
+
+/**
+ * Fetch contributions grouped by repository, busiest repository first
+ * @param {string|null} username - GitHub login (defaults to the active account)
+ * @param {string|null} from - Inclusive start date (YYYY-MM-DD), defaults to one year ago
+ * @param {string|null} to - Inclusive end date (YYYY-MM-DD), defaults to today
+ * @returns {Promise<Object>} {repositories, truncated}: repositories are {nameWithOwner, isPrivate,
+ *   primaryLanguage, totalCount, days}; truncated is true when GitHub listed only its first 100
+ *   repositories for a contribution type and year, so less active ones are missing
+ */
+export async function fetchRepositoryContributionsViaTauri(username, from = null, to = null) {
+  try {
+    const result = await invoke('fetch_repository_contributions', {
+      username: username || undefined,
+      from: from || undefined,
+      to: to || undefined,
+    });
+
+    if (result.ok && result.data) {
+      return result.data;
+    } else {
+      console.error('Tauri repository fetch error:', result.error);
+      throw toError(result.error);
+    }
+  } catch (error) {
+    console.error('Failed to fetch repositories via Tauri:', error);
+    throw toError(error);
+  }
+}
+
+/**
+ * Count commits in local git repositories, without network access or a token
+ * @param {string[]} directories - Directories to search for repositories
+ * @param {string[]} emails - Author emails that count as the user
+ * @param {string|null} from - Inclusive start date (YYYY-MM-DD), defaults to the heatmap's year
+ * @param {string|null} to - Inclusive end date (YYYY-MM-DD), defaults to today
+ * @returns {Promise<Object>} {days, failures}: contribution days, and {path, message} for each
+ *   repository that could not be read and was skipped
+ */
+export async function fetchLocalContributionsViaTauri(directories, emails, from = null, to = null) {
+  try {
+    const result = await invoke('fetch_local_contributions', {
+      directories,
+      emails,
+      from: from || undefined,
+      to: to || undefined,
+    });
+
+    if (result.ok && result.data) {
+      return result.data;
+    } else {
+      console.error('Tauri local scan error:', result.error);
+      throw toError(result.error);
+    }
+  } catch (error) {
+    console.error('Failed to scan local repositories via Tauri:', error);
+    throw toError(error);
+  }
+}
+
+/**
+ * Fetch several accounts (GitHub, GitLab, Gitea, ...) and sum them into one heatmap
+ * @param {Array<{username: string, provider?: string, host?: string, label?: string}>} profiles
+ * @returns {Promise<{days: Array, bySource: Object, failures: Array<{label: string, error: string}>}>}
+ */
+export async function fetchMergedContributionsViaTauri(profiles) {
+  try {
+    const result = await invoke('fetch_merged_contributions', { profiles });
+
+    if (result.ok && result.data) {
+      result.data.failures.forEach(failure => {
+        console.warn(`Source ${failure.label} failed:`, failure.error);
+      });
+      return result.data;
+    } else {
+      console.error('Tauri merged fetch error:', result.error);
+      throw toError(result.error);
+    }
+  } catch (error) {
+    console.error('Failed to fetch merged contributions via Tauri:', error);
+    throw toError(error);
+  }
+}
+
+/**
+ * Stored daily counts of an account, kept beyond the trailing year
+ * @param {string|null} username - Account login (defaults to the active account)
+ * @param {string|null} from - Inclusive start date (YYYY-MM-DD), unbounded when omitted
+ * @param {string|null} to - Inclusive end date (YYYY-MM-DD), unbounded when omitted
+ * @returns {Promise<Array<{date: string, contributionCount: number, firstSeen: number, lastSeen: number}>>}
+ */
+export async function fetchContributionHistory(username = null, from = null, to = null) {
+  try {
+    return await invoke('fetch_contribution_history', {
+      username: username || undefined,
+      from: from || undefined,
+      to: to || undefined,
+    });
+  } catch (error) {
+    console.error('Failed to read contribution history:', error);
+    throw toError(error);
+  }
+}
+
+/**
+ * Past days whose counts changed after they were first fetched
+ * @param {string|null} username - Account login (defaults to the active account)
+ * @param {string|null} from - Inclusive start date (YYYY-MM-DD), unbounded when omitted
+ * @param {string|null} to - Inclusive end date (YYYY-MM-DD), unbounded when omitted
+ * @returns {Promise<Array<{date: string, previousCount: number, contributionCount: number, observedAt: number}>>}
+ */
+export async function fetchHistoryChanges(username = null, from = null, to = null) {
+  try {
+    return await invoke('fetch_history_changes', {
+      username: username || undefined,
+      from: from || undefined,
+      to: to || undefined,
+    });
+  } catch (error) {
+    console.error('Failed to read history changes:', error);
+    throw toError(error);
+  }
+}
+
+/**
+ * Streaks, busiest day/week/month, active-day mean and median and weekday
+ * totals of a calendar, computed by the backend
+ * @param {Array} days - Contribution days
+ * @returns {Promise<Object>}
+ */
+export async function computeContributionStats(days) {
+  // The user's date, so "today has no contributions yet" keeps a streak alive
+  const now = new Date();
+  const today = [
+    now.getFullYear(),
+    String(now.getMonth() + 1).padStart(2, '0'),
+    String(now.getDate()).padStart(2, '0'),
+  ].join('-');
+
+  try {
+    return await invoke('compute_contribution_stats', { days, today });
+  } catch (error) {
+    throw toError(error);
+  }
+}
+
+/**
+ * Render a calendar as a standalone SVG document, for embedding elsewhere
+ * @param {Array} days - Contribution days
+ * @param {{theme?: 'light'|'dark', title?: string, showStats?: boolean}} options
+ * @returns {Promise<string>} SVG markup
+ */
+export async function renderHeatmapSvg(days, { theme, title, showStats = false } = {}) {
+  try {
+    return await invoke('render_heatmap_svg', { days, theme, title, showStats });
+  } catch (error) {
+    throw toError(error);
+  }
+}
+
+/**
+ * Render a calendar to a PNG file without the webview
+ * @param {Array} days - Contribution days
+ * @param {string} path - Where to write the image
+ * @param {{scale?: number, theme?: 'light'|'dark', title?: string, showStats?: boolean}} options
+ * @returns {Promise<string>} The path written
+ */
+export async function exportHeatmapPng(days, path, { scale = 1, theme, title, showStats = false } = {}) {
+  try {
+    return await invoke('export_heatmap_png', { days, path, scale, theme, title, showStats });
+  } catch (error) {
+    throw toError(error);
   }
 }
 
@@ -39,33 +284,282 @@ export async function clearCache() {
     return await invoke('clear_cache');
   } catch (error) {
     console.error('Failed to clear cache:', error);
-    throw error;
+    throw toError(error);
+  }
+}
+
+/**
+ * Current API budget for each host, token and resource ('core' for REST, 'graphql') seen this session
+ * @returns {Promise<Array<{host: string, resource: string, authenticated: boolean, limit: number|null, remaining: number|null, resetAt: number|null, blockedUntil: number|null, lastCost: number|null, updatedAt: number}>>}
+ */
+export async function getRateLimitStatus() {
+  return await invoke('get_rate_limit_status');
+}
+
+/**
+ * Listen for background refreshes of stale cache entries. Fetches may return
+ * an expired entry (`stale: true`) right away and refresh it afterwards; each
+ * refresh then reports `{ cacheKey, ok, data, error }`. A failed refresh
+ * leaves the stale entry cached.
+ * @param {Function} callback - Called with each refresh's payload
+ * @returns {Promise<Function>} Unlisten function
+ */
+export async function onCacheRefreshed(callback) {
+  return await listen('cache-refreshed', (event) => callback(event.payload));
+}
+
+/**
+ * Listen for the backend scheduler's refreshes of the active account. Only
+ * sent when the calendar changed; the payload is `{ username, host, days }`.
+ * @param {Function} callback - Called with each update's payload
+ * @returns {Promise<Function>} Unlisten function
+ */
+export async function onContributionsUpdated(callback) {
+  return await listen('contributions-updated', (event) => callback(event.payload));
+}
+
+/**
+ * Persisted settings: accounts, cache TTL, refresh interval, theme, week
+ * start and level thresholds
+ * @returns {Promise<Object>}
+ */
+export async function getSettings() {
+  try {
+    return await invoke('get_settings');
+  } catch (error) {
+    throw toError(error);
+  }
+}
+
+/**
+ * Change preferences; omitted fields keep their value. Invalid values are
+ * rejected with `INVALID_INPUT`.
+ * @param {Object} update - e.g. `{ theme: 'dark', levelScale: 'fixed', levelThresholds: [1, 4, 8, 12] }`
+ * @returns {Promise<Object>} The saved settings
+ */
+export async function updateSettings(update) {
+  try {
+    return await invoke('update_settings', { update });
+  } catch (error) {
+    throw toError(error);
   }
 }
 
-export async function saveGitHubToken(token) {
+/**
+ * Listen for saved settings, including changes made by account commands
+ * @param {Function} callback - Called with the new settings
+ * @returns {Promise<Function>} Unlisten function
+ */
+export async function onSettingsChanged(callback) {
+  return await listen('settings-changed', (event) => callback(event.payload));
+}
+
+/**
+ * Write an account's contributions to a file
+ * @param {string} format - 'csv', 'json', 'ndjson' or 'ics'
+ * @param {string} path - Where to write the file
+ * @param {{username?: string, from?: string, to?: string}} options - Account
+ *   (defaults to the active one) and inclusive YYYY-MM-DD range (defaults to the trailing year)
+ * @returns {Promise<{path: string, rows: number}>}
+ */
+export async function exportContributions(format, path, { username, from, to } = {}) {
   try {
-    await invoke('save_github_token', { token });
+    return await invoke('export_contributions', {
+      format,
+      path,
+      username: username || undefined,
+      from: from || undefined,
+      to: to || undefined,
+    });
+  } catch (error) {
+    console.error('Failed to export contributions:', error);
+    throw toError(error);
+  }
+}
+
+export async function getRefreshInterval() {
+  return await invoke('get_refresh_interval');
+}
+
+export async function setRefreshInterval(seconds) {
+  return await invoke('set_refresh_interval', { seconds });
+}
+
+export async function getRetryPolicy() {
+  return await invoke('get_retry_policy');
+}
+
+export async function setRetryPolicy(policy) {
+  return await invoke('set_retry_policy', { policy });
+}
+
+export async function cancelPendingFetches() {
+  await invoke('cancel_pending_fetches');
+}
+
+export async function saveGitHubToken(token, host = null) {
+  try {
+    await invoke('save_github_token', { token, host: host || undefined });
   } catch (error) {
     console.error('Failed to save token:', error);
-    throw error;
+    throw toError(error);
   }
 }
 
-export async function getGitHubToken() {
+/**
+ * Report whether an account has a stored token, with its scopes and expiry (never the token itself)
+ * @param {string|null} id - Account id (defaults to the active account)
+ * @returns {Promise<{account: Object|null, hasToken: boolean, scopes: string[]|null, expiresAt: string|null}>}
+ */
+export async function getTokenStatus(id = null) {
   try {
-    return await invoke('get_github_token');
+    return await invoke('get_token_status', { id: id || undefined });
   } catch (error) {
-    // It's normal to fail if no token exists yet
-    return null;
+    console.error('Failed to get token status:', error);
+    return { account: null, hasToken: false, scopes: null, expiresAt: null };
   }
 }
 
-export async function deleteGitHubToken() {
+export async function deleteGitHubToken(host = null) {
   try {
-    await invoke('delete_github_token');
+    await invoke('delete_github_token', { host: host || undefined });
   } catch (error) {
     console.error('Failed to delete token:', error);
-    throw error;
+    throw toError(error);
   }
 }
+
+/**
+ * Check a token against /user on github.com or a GitHub Enterprise Server host before saving it
+ * @param {string} token - Personal access token to check
+ * @param {string|null} host - GitHub host or URL (defaults to github.com)
+ * @returns {Promise<{login: string, name: string|null, avatarUrl: string|null, scopes: string[]|null, expiresAt: string|null, expiresIn: number|null, warnings: string[]}>}
+ */
+export async function validateGitHubToken(token, host = null) {
+  return await invoke('validate_github_token', { token, host: host || undefined });
+}
+
+/**
+ * Report which credential backend stores tokens (OS keyring or encrypted file)
+ * @returns {Promise<{backend: string, keySource: string, keyringAvailable: boolean, locked: boolean}>}
+ */
+export async function getCredentialBackend() {
+  return await invoke('get_credential_backend');
+}
+
+/**
+ * Unlock a passphrase-protected credentials file for this session
+ * @param {string} passphrase - Passphrase the file was encrypted with
+ */
+export async function unlockCredentials(passphrase) {
+  await invoke('unlock_credentials', { passphrase });
+}
+
+/**
+ * Move all stored tokens to another credential backend and switch to it
+ * @param {'keyring'|'encryptedFile'} backend - Target backend
+ * @param {'machine'|'passphrase'|null} keySource - Key for the encrypted file (defaults to machine)
+ * @param {string|null} passphrase - Required when keySource is 'passphrase'
+ * @returns {Promise<number>} Number of tokens moved
+ */
+export async function migrateCredentials(backend, keySource = null, passphrase = null) {
+  return await invoke('migrate_credentials', {
+    backend,
+    keySource: keySource || undefined,
+    passphrase: passphrase || undefined,
+  });
+}
+
+/**
+ * Store the provider and host used by an account
+ * @param {string} username - Account login
+ * @param {string} host - Host or URL (empty for the provider's public instance); an `http://` prefix is kept
+ *   for GitLab and Gitea, and refused for GitHub
+ * @param {string} provider - 'github', 'gitlab' or 'gitea' (also Forgejo and Codeberg)
+ * @returns {Promise<{provider: string, host: string, scheme: string}>} Normalized settings
+ */
+export async function setAccountHost(username, host, provider = 'github') {
+  return await invoke('set_account_host', { username, host, provider });
+}
+
+/**
+ * Get the provider and host stored for an account (GitHub on github.com if none was stored)
+ * @param {string} username - Account login
+ * @returns {Promise<{provider: string, host: string, scheme: string}>}
+ */
+export async function getAccountConfig(username) {
+  try {
+    return await invoke('get_account_config', { username });
+  } catch (error) {
+    return { provider: 'github', host: 'github.com', scheme: 'https' };
+  }
+}
+
+/**
+ * Get the host stored for an account
+ * @param {string} username - Account login
+ * @returns {Promise<string>} Host
+ */
+export async function getAccountHost(username) {
+  return (await getAccountConfig(username)).host;
+}
+
+/**
+ * List stored accounts
+ * @returns {Promise<{active: string|null, accounts: Array<{id: string, provider: string, host: string, scheme: string, login: string, label: string|null}>}>}
+ */
+export async function listAccounts() {
+  return await invoke('list_accounts');
+}
+
+/**
+ * Add an account; its token is kept in the OS keyring under the account's own key
+ * @param {{login: string, provider?: string, host?: string, label?: string, token?: string}} account
+ * @returns {Promise<Object>} The stored account
+ */
+export async function addAccount({ login, provider, host, label, token }) {
+  return await invoke('add_account', { login, provider, host, label, token });
+}
+
+/**
+ * Make an account the active one
+ * @param {string} id - Account id
+ * @returns {Promise<Object>} The now active account
+ */
+export async function switchAccount(id) {
+  return await invoke('switch_account', { id });
+}
+
+/**
+ * Remove an account, its token and its cached data
+ * @param {string} id - Account id
+ */
+export async function removeAccount(id) {
+  await invoke('remove_account', { id });
+}
+
+/**
+ * Start a GitHub OAuth device-flow login. Fails with code LOGIN_FAILED and reason
+ * 'not_configured' when no client id is passed and the build has none.
+ * @param {string|null} host - GitHub host (defaults to github.com)
+ * @param {string|null} clientId - OAuth app client id (defaults to the one built in)
+ * @returns {Promise<{deviceCode: string, userCode: string, verificationUri: string, expiresIn: number, interval: number}>}
+ */
+export async function startDeviceLogin(host = null, clientId = null) {
+  return await invoke('start_device_login', { host: host || undefined, clientId: clientId || undefined });
+}
+
+/**
+ * Wait until the user approves the device-flow login; the token is stored by the backend
+ * @param {Object} deviceCode - Result of startDeviceLogin
+ * @param {string|null} host - GitHub host (defaults to github.com)
+ * @param {string|null} clientId - The client id passed to startDeviceLogin, if any
+ * @returns {Promise<Object>} The new, now active account
+ */
+export async function completeDeviceLogin(deviceCode, host = null, clientId = null) {
+  return await invoke('complete_device_login', {
+    deviceCode,
+    host: host || undefined,
+    clientId: clientId || undefined,
+  });
+}